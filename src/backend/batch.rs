@@ -0,0 +1,194 @@
+/// Write operations that are applied together through [`CacheBackend::apply`](super::CacheBackend::apply)
+#[derive(Debug, Default)]
+pub struct Batch {
+    ops: Vec<BatchOp>,
+    prefix: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum BatchOp {
+    /// Store a value, optionally expiring after the given amount of seconds
+    Set {
+        key: String,
+        value: Vec<u8>,
+        ttl: Option<usize>,
+    },
+    /// Remove the value or set stored under the key
+    Del { key: String },
+    /// Remove the value stored under the key and the key's entry in the index set,
+    /// but only if the set of references to the key is empty
+    DelUnreferenced {
+        key: String,
+        refs: String,
+        index: String,
+        entry: String,
+    },
+    /// Add the delta to the integer stored under the key, starting from 0 if there is none
+    Incr { key: String, delta: i64 },
+    /// Add members to a set
+    SetAdd { key: String, members: Vec<String> },
+    /// Remove members from a set
+    SetRemove { key: String, members: Vec<String> },
+    /// Add members with their score to a sorted set, updating the score of existing members
+    SortedSetAdd {
+        key: String,
+        members: Vec<(u64, String)>,
+    },
+    /// Remove members from a sorted set
+    SortedSetRemove { key: String, members: Vec<String> },
+    /// Remove a member from a sorted set and the given entries from their sets,
+    /// but only if the member's score is still at most `max_score`
+    SortedSetRemoveExpired {
+        key: String,
+        member: String,
+        max_score: u64,
+        sets: Vec<(String, String)>,
+    },
+    /// Push values to the front of a list, the last value ending up first,
+    /// and trim the list to the given length which is never 0
+    ListPush {
+        key: String,
+        values: Vec<Vec<u8>>,
+        max_len: usize,
+    },
+    /// Insert the value in front of the first occurrence of the pivot in a list,
+    /// nothing is inserted if the pivot is not part of the list
+    ListInsert {
+        key: String,
+        pivot: Vec<u8>,
+        value: Vec<u8>,
+    },
+    /// Remove the first occurrence of the value from a list
+    ListRemove { key: String, value: Vec<u8> },
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    #[inline]
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    /// Create a batch that prepends the prefix to the keys of all its operations
+    pub(crate) fn with_prefix(prefix: Option<String>) -> Self {
+        Self {
+            ops: Vec::new(),
+            prefix,
+        }
+    }
+
+    fn key(&self, key: String) -> String {
+        match self.prefix {
+            Some(ref prefix) => format!("{}:{}", prefix, key),
+            None => key,
+        }
+    }
+
+    pub(crate) fn set(&mut self, key: String, value: Vec<u8>, ttl: Option<usize>) {
+        let key = self.key(key);
+        self.ops.push(BatchOp::Set { key, value, ttl });
+    }
+
+    pub(crate) fn del(&mut self, key: String) {
+        let key = self.key(key);
+        self.ops.push(BatchOp::Del { key });
+    }
+
+    pub(crate) fn del_unreferenced(&mut self, key: String, refs: String, index: String) {
+        let entry = key.clone();
+        let key = self.key(key);
+        let refs = self.key(refs);
+        let index = self.key(index);
+
+        self.ops.push(BatchOp::DelUnreferenced {
+            key,
+            refs,
+            index,
+            entry,
+        });
+    }
+
+    pub(crate) fn incr(&mut self, key: String, delta: i64) {
+        let key = self.key(key);
+        self.ops.push(BatchOp::Incr { key, delta });
+    }
+
+    pub(crate) fn set_add(&mut self, key: String, members: Vec<String>) {
+        if !members.is_empty() {
+            let key = self.key(key);
+            self.ops.push(BatchOp::SetAdd { key, members });
+        }
+    }
+
+    pub(crate) fn set_remove(&mut self, key: String, members: Vec<String>) {
+        if !members.is_empty() {
+            let key = self.key(key);
+            self.ops.push(BatchOp::SetRemove { key, members });
+        }
+    }
+
+    pub(crate) fn sorted_set_add(&mut self, key: String, members: Vec<(u64, String)>) {
+        if !members.is_empty() {
+            let key = self.key(key);
+            self.ops.push(BatchOp::SortedSetAdd { key, members });
+        }
+    }
+
+    pub(crate) fn sorted_set_remove_expired(
+        &mut self,
+        key: String,
+        member: String,
+        max_score: u64,
+        sets: Vec<(String, String)>,
+    ) {
+        let key = self.key(key);
+
+        let sets = sets
+            .into_iter()
+            .map(|(set, entry)| (self.key(set), entry))
+            .collect();
+
+        self.ops.push(BatchOp::SortedSetRemoveExpired {
+            key,
+            member,
+            max_score,
+            sets,
+        });
+    }
+
+    pub(crate) fn list_push(&mut self, key: String, values: Vec<Vec<u8>>, max_len: usize) {
+        if !values.is_empty() && max_len > 0 {
+            let key = self.key(key);
+            self.ops.push(BatchOp::ListPush {
+                key,
+                values,
+                max_len,
+            });
+        }
+    }
+
+    pub(crate) fn list_insert(&mut self, key: String, pivot: Vec<u8>, value: Vec<u8>) {
+        let key = self.key(key);
+        self.ops.push(BatchOp::ListInsert { key, pivot, value });
+    }
+
+    pub(crate) fn list_remove(&mut self, key: String, value: Vec<u8>) {
+        let key = self.key(key);
+        self.ops.push(BatchOp::ListRemove { key, value });
+    }
+
+    pub(crate) fn sorted_set_remove(&mut self, key: String, members: Vec<String>) {
+        if !members.is_empty() {
+            let key = self.key(key);
+            self.ops.push(BatchOp::SortedSetRemove { key, members });
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use hashbrown::{HashMap, HashSet};
+
+use crate::CacheResult;
+
+use super::{Batch, BatchOp, CacheBackend};
+
+/// [`CacheBackend`] storing everything in process memory.
+///
+/// Mirrors the redis semantics the cache relies on, including expiring values,
+/// so it can be used to run the cache without an external service e.g. in tests.
+#[derive(Default)]
+pub struct MemoryBackend {
+    storage: Mutex<Storage>,
+}
+
+#[derive(Default)]
+struct Storage {
+    values: HashMap<String, Entry>,
+    sets: HashMap<String, HashSet<String>>,
+    sorted_sets: HashMap<String, HashMap<String, u64>>,
+    lists: HashMap<String, VecDeque<Vec<u8>>>,
+}
+
+struct Entry {
+    value: Vec<u8>,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at
+            .filter(|expires_at| *expires_at <= now)
+            .is_some()
+    }
+}
+
+impl Storage {
+    fn value(&mut self, key: &str) -> Option<&Entry> {
+        let now = Instant::now();
+
+        if self.values.get(key)?.is_expired(now) {
+            self.values.remove(key);
+
+            return None;
+        }
+
+        self.values.get(key)
+    }
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn storage(&self) -> MutexGuard<'_, Storage> {
+        self.storage.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryBackend {
+    async fn get(&self, key: &str) -> CacheResult<Option<Vec<u8>>> {
+        Ok(self.storage().value(key).map(|entry| entry.value.clone()))
+    }
+
+    async fn get_many(&self, keys: &[String]) -> CacheResult<Vec<Option<Vec<u8>>>> {
+        let mut storage = self.storage();
+
+        let values = keys
+            .iter()
+            .map(|key| storage.value(key).map(|entry| entry.value.clone()))
+            .collect();
+
+        Ok(values)
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        let mut storage = self.storage();
+
+        Ok(storage.value(key).is_some()
+            || storage.sets.contains_key(key)
+            || storage.sorted_sets.contains_key(key)
+            || storage.lists.contains_key(key))
+    }
+
+    async fn apply(&self, batch: &Batch) -> CacheResult<()> {
+        let now = Instant::now();
+        let mut storage = self.storage();
+
+        for op in batch.ops() {
+            match op {
+                BatchOp::Set { key, value, ttl } => {
+                    let entry = Entry {
+                        value: value.to_owned(),
+                        expires_at: ttl.map(|seconds| now + Duration::from_secs(seconds as u64)),
+                    };
+
+                    storage.sets.remove(key);
+                    storage.sorted_sets.remove(key);
+                    storage.lists.remove(key);
+                    storage.values.insert(key.to_owned(), entry);
+                }
+                BatchOp::Del { key } => {
+                    storage.values.remove(key);
+                    storage.sets.remove(key);
+                    storage.sorted_sets.remove(key);
+                    storage.lists.remove(key);
+                }
+                BatchOp::DelUnreferenced {
+                    key,
+                    refs,
+                    index,
+                    entry,
+                } => {
+                    if storage.sets.contains_key(refs) {
+                        continue;
+                    }
+
+                    storage.values.remove(key);
+
+                    if let Some(set) = storage.sets.get_mut(index) {
+                        set.remove(entry);
+
+                        if set.is_empty() {
+                            storage.sets.remove(index);
+                        }
+                    }
+                }
+                BatchOp::Incr { key, delta } => {
+                    // Redis stores integers as their decimal representation
+                    let value = storage
+                        .value(key)
+                        .and_then(|entry| std::str::from_utf8(&entry.value).ok())
+                        .and_then(|value| value.parse::<i64>().ok())
+                        .unwrap_or(0);
+
+                    let expires_at = storage.value(key).and_then(|entry| entry.expires_at);
+
+                    let entry = Entry {
+                        value: (value + delta).to_string().into_bytes(),
+                        expires_at,
+                    };
+
+                    storage.values.insert(key.to_owned(), entry);
+                }
+                BatchOp::SetAdd { key, members } => {
+                    storage.values.remove(key);
+
+                    storage
+                        .sets
+                        .entry(key.to_owned())
+                        .or_insert_with(HashSet::new)
+                        .extend(members.iter().cloned());
+                }
+                BatchOp::SetRemove { key, members } => {
+                    if let Some(set) = storage.sets.get_mut(key) {
+                        for member in members {
+                            set.remove(member);
+                        }
+
+                        // Redis removes sets once they're empty
+                        if set.is_empty() {
+                            storage.sets.remove(key);
+                        }
+                    }
+                }
+                BatchOp::SortedSetAdd { key, members } => {
+                    let set = storage
+                        .sorted_sets
+                        .entry(key.to_owned())
+                        .or_insert_with(HashMap::new);
+
+                    for (score, member) in members {
+                        set.insert(member.to_owned(), *score);
+                    }
+                }
+                BatchOp::SortedSetRemove { key, members } => {
+                    if let Some(set) = storage.sorted_sets.get_mut(key) {
+                        for member in members {
+                            set.remove(member);
+                        }
+
+                        if set.is_empty() {
+                            storage.sorted_sets.remove(key);
+                        }
+                    }
+                }
+                BatchOp::SortedSetRemoveExpired {
+                    key,
+                    member,
+                    max_score,
+                    sets,
+                } => {
+                    let expired = storage
+                        .sorted_sets
+                        .get(key)
+                        .and_then(|set| set.get(member))
+                        .filter(|score| *score <= max_score)
+                        .is_some();
+
+                    if !expired {
+                        continue;
+                    }
+
+                    for (set_key, entry) in sets {
+                        if let Some(set) = storage.sets.get_mut(set_key) {
+                            set.remove(entry);
+
+                            if set.is_empty() {
+                                storage.sets.remove(set_key);
+                            }
+                        }
+                    }
+
+                    if let Some(set) = storage.sorted_sets.get_mut(key) {
+                        set.remove(member);
+
+                        if set.is_empty() {
+                            storage.sorted_sets.remove(key);
+                        }
+                    }
+                }
+                BatchOp::ListPush {
+                    key,
+                    values,
+                    max_len,
+                } => {
+                    let list = storage
+                        .lists
+                        .entry(key.to_owned())
+                        .or_insert_with(VecDeque::new);
+
+                    for value in values {
+                        list.push_front(value.to_owned());
+                    }
+
+                    list.truncate(*max_len);
+
+                    if list.is_empty() {
+                        storage.lists.remove(key);
+                    }
+                }
+                BatchOp::ListInsert { key, pivot, value } => {
+                    if let Some(list) = storage.lists.get_mut(key) {
+                        if let Some(index) = list.iter().position(|elem| elem == pivot) {
+                            list.insert(index, value.to_owned());
+                        }
+                    }
+                }
+                BatchOp::ListRemove { key, value } => {
+                    if let Some(list) = storage.lists.get_mut(key) {
+                        if let Some(index) = list.iter().position(|elem| elem == value) {
+                            list.remove(index);
+                        }
+
+                        if list.is_empty() {
+                            storage.lists.remove(key);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_members(&self, key: &str) -> CacheResult<Vec<Vec<u8>>> {
+        let storage = self.storage();
+
+        let members = storage
+            .sets
+            .get(key)
+            .map(|set| {
+                set.iter()
+                    .map(|member| member.as_bytes().to_vec())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(members)
+    }
+
+    async fn set_contains(&self, key: &str, member: &str) -> CacheResult<bool> {
+        let storage = self.storage();
+
+        Ok(storage
+            .sets
+            .get(key)
+            .filter(|set| set.contains(member))
+            .is_some())
+    }
+
+    async fn set_scan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> CacheResult<(u64, Vec<Vec<u8>>)> {
+        let storage = self.storage();
+
+        let mut members: Vec<_> = match storage.sets.get(key) {
+            Some(set) => set.iter().collect(),
+            None => return Ok((0, Vec::new())),
+        };
+
+        // The cursor is the offset into the sorted members
+        members.sort_unstable();
+
+        let start = (cursor as usize).min(members.len());
+        let end = (start + count).min(members.len());
+        let next = if end == members.len() { 0 } else { end as u64 };
+
+        let page = members[start..end]
+            .iter()
+            .map(|member| member.as_bytes().to_vec())
+            .collect();
+
+        Ok((next, page))
+    }
+
+    async fn set_len(&self, key: &str) -> CacheResult<usize> {
+        Ok(self.storage().sets.get(key).map_or(0, HashSet::len))
+    }
+
+    async fn list_front(&self, key: &str, count: usize) -> CacheResult<Vec<Vec<u8>>> {
+        let storage = self.storage();
+
+        let elems = storage
+            .lists
+            .get(key)
+            .map(|list| list.iter().take(count).cloned().collect())
+            .unwrap_or_default();
+
+        Ok(elems)
+    }
+
+    async fn sorted_set_range(&self, key: &str, max_score: u64) -> CacheResult<Vec<Vec<u8>>> {
+        let storage = self.storage();
+
+        let mut members: Vec<_> = storage
+            .sorted_sets
+            .get(key)
+            .map(|set| {
+                set.iter()
+                    .filter(|(_, score)| **score <= max_score)
+                    .map(|(member, score)| (*score, member.as_bytes().to_vec()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Redis orders members by their score
+        members.sort_unstable();
+
+        Ok(members.into_iter().map(|(_, member)| member).collect())
+    }
+}
@@ -0,0 +1,59 @@
+mod batch;
+mod memory;
+mod redis;
+
+pub use self::{
+    batch::{Batch, BatchOp},
+    memory::MemoryBackend,
+    redis::RedisBackend,
+};
+
+use async_trait::async_trait;
+
+use crate::CacheResult;
+
+/// Storage that the [`Cache`](crate::Cache) reads from and writes to.
+///
+/// Keys and set members are the string representations of
+/// [`RedisKey`](crate::model::RedisKey)s and index set names,
+/// values are cbor encoded bytes.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Retrieve the value stored under the key
+    async fn get(&self, key: &str) -> CacheResult<Option<Vec<u8>>>;
+
+    /// Retrieve the values stored under the keys, in the same order as the keys
+    async fn get_many(&self, keys: &[String]) -> CacheResult<Vec<Option<Vec<u8>>>>;
+
+    /// Check whether a value or set is stored under the key
+    async fn exists(&self, key: &str) -> CacheResult<bool>;
+
+    /// Apply all operations of the batch in order and atomically
+    async fn apply(&self, batch: &Batch) -> CacheResult<()>;
+
+    /// Retrieve all members of the set stored under the key
+    async fn set_members(&self, key: &str) -> CacheResult<Vec<Vec<u8>>>;
+
+    /// Check whether the member is part of the set stored under the key
+    async fn set_contains(&self, key: &str, member: &str) -> CacheResult<bool>;
+
+    /// Retrieve a page of the set stored under the key, starting at the cursor.
+    ///
+    /// Returns the cursor of the next page, `0` if there are no further pages.
+    async fn set_scan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> CacheResult<(u64, Vec<Vec<u8>>)>;
+
+    /// Retrieve the amount of members of the set stored under the key
+    async fn set_len(&self, key: &str) -> CacheResult<usize>;
+
+    /// Retrieve up to `count` elements from the front of the list stored under the key
+    async fn list_front(&self, key: &str, count: usize) -> CacheResult<Vec<Vec<u8>>>;
+
+    /// Retrieve all members of the sorted set stored under the key
+    /// whose score is at most `max_score`
+    async fn sorted_set_range(&self, key: &str, max_score: u64) -> CacheResult<Vec<Vec<u8>>>;
+}
@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use deadpool_redis::{
+    redis::{self, AsyncCommands},
+    Pool,
+};
+
+use crate::CacheResult;
+
+use super::{Batch, BatchOp, CacheBackend};
+
+/// Remove `ARGV[1]` from the sorted set `KEYS[1]` if its score is at most `ARGV[2]`,
+/// in which case `ARGV[i + 1]` is also removed from the set `KEYS[i]` for all further keys
+const REMOVE_EXPIRED_SCRIPT: &str = r#"
+local score = redis.call('ZSCORE', KEYS[1], ARGV[1])
+
+if score and tonumber(score) <= tonumber(ARGV[2]) then
+    for i = 2, #KEYS do
+        redis.call('SREM', KEYS[i], ARGV[i + 1])
+    end
+
+    redis.call('ZREM', KEYS[1], ARGV[1])
+end
+"#;
+
+/// Delete `KEYS[1]` and remove `ARGV[1]` from the set `KEYS[3]` if the set `KEYS[2]` is empty
+const DEL_UNREFERENCED_SCRIPT: &str = r#"
+if redis.call('SCARD', KEYS[2]) == 0 then
+    redis.call('DEL', KEYS[1])
+    redis.call('SREM', KEYS[3], ARGV[1])
+end
+"#;
+
+/// [`CacheBackend`] storing everything in redis
+pub struct RedisBackend {
+    pool: Pool,
+}
+
+impl RedisBackend {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+impl From<Pool> for RedisBackend {
+    fn from(pool: Pool) -> Self {
+        Self::new(pool)
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisBackend {
+    async fn get(&self, key: &str) -> CacheResult<Option<Vec<u8>>> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.get(key).await?)
+    }
+
+    async fn get_many(&self, keys: &[String]) -> CacheResult<Vec<Option<Vec<u8>>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.pool.get().await?;
+
+        Ok(redis::cmd("MGET").arg(keys).query_async(&mut conn).await?)
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.exists(key).await?)
+    }
+
+    async fn apply(&self, batch: &Batch) -> CacheResult<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+
+        for op in batch.ops() {
+            match op {
+                BatchOp::Set {
+                    key,
+                    value,
+                    ttl: None,
+                } => pipe.set(key, value).ignore(),
+                BatchOp::Set {
+                    key,
+                    value,
+                    ttl: Some(seconds),
+                } => pipe.set_ex(key, value, *seconds).ignore(),
+                BatchOp::Del { key } => pipe.del(key).ignore(),
+                BatchOp::DelUnreferenced {
+                    key,
+                    refs,
+                    index,
+                    entry,
+                } => pipe
+                    .cmd("EVAL")
+                    .arg(DEL_UNREFERENCED_SCRIPT)
+                    .arg(3)
+                    .arg(key)
+                    .arg(refs)
+                    .arg(index)
+                    .arg(entry)
+                    .ignore(),
+                BatchOp::Incr { key, delta } => pipe.incr(key, *delta).ignore(),
+                BatchOp::SetAdd { key, members } => pipe.sadd(key, members).ignore(),
+                BatchOp::SetRemove { key, members } => pipe.srem(key, members).ignore(),
+                BatchOp::SortedSetAdd { key, members } => pipe.zadd_multiple(key, members).ignore(),
+                BatchOp::SortedSetRemove { key, members } => pipe.zrem(key, members).ignore(),
+                BatchOp::SortedSetRemoveExpired {
+                    key,
+                    member,
+                    max_score,
+                    sets,
+                } => {
+                    // Checking the score and removing happens in a single step so that
+                    // members that were cached again in the meantime are kept
+                    let cmd = pipe
+                        .cmd("EVAL")
+                        .arg(REMOVE_EXPIRED_SCRIPT)
+                        .arg(1 + sets.len())
+                        .arg(key);
+
+                    for (set, _) in sets {
+                        cmd.arg(set);
+                    }
+
+                    cmd.arg(member).arg(*max_score);
+
+                    for (_, entry) in sets {
+                        cmd.arg(entry);
+                    }
+
+                    cmd.ignore()
+                }
+                BatchOp::ListPush {
+                    key,
+                    values,
+                    max_len,
+                } => {
+                    pipe.lpush(key, values).ignore();
+
+                    pipe.ltrim(key, 0, list_index(*max_len) - 1).ignore()
+                }
+                BatchOp::ListInsert { key, pivot, value } => {
+                    pipe.linsert_before(key, pivot, value).ignore()
+                }
+                BatchOp::ListRemove { key, value } => pipe.lrem(key, 1, value).ignore(),
+            };
+        }
+
+        let mut conn = self.pool.get().await?;
+        pipe.query_async::<_, ()>(&mut conn).await?;
+
+        Ok(())
+    }
+
+    async fn set_members(&self, key: &str) -> CacheResult<Vec<Vec<u8>>> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.smembers(key).await?)
+    }
+
+    async fn set_contains(&self, key: &str, member: &str) -> CacheResult<bool> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.sismember(key, member).await?)
+    }
+
+    async fn set_scan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> CacheResult<(u64, Vec<Vec<u8>>)> {
+        let mut conn = self.pool.get().await?;
+
+        let page = redis::cmd("SSCAN")
+            .arg(key)
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(page)
+    }
+
+    async fn set_len(&self, key: &str) -> CacheResult<usize> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.scard(key).await?)
+    }
+
+    async fn list_front(&self, key: &str, count: usize) -> CacheResult<Vec<Vec<u8>>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.lrange(key, 0, list_index(count) - 1).await?)
+    }
+
+    async fn sorted_set_range(&self, key: &str, max_score: u64) -> CacheResult<Vec<Vec<u8>>> {
+        let mut conn = self.pool.get().await?;
+
+        Ok(conn.zrangebyscore(key, "-inf", max_score).await?)
+    }
+}
+
+fn list_index(index: usize) -> isize {
+    index.min(isize::MAX as usize) as isize
+}
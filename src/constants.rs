@@ -6,10 +6,33 @@ pub(crate) const GUILD_KEY: &str = "guild";
 pub(crate) const CHANNEL_KEY: &str = "channel";
 pub(crate) const ROLE_KEY: &str = "role";
 pub(crate) const MEMBER_KEY: &str = "member";
+pub(crate) const USER_KEY: &str = "user";
+pub(crate) const VOICE_STATE_KEY: &str = "voice_state";
+pub(crate) const PRESENCE_KEY: &str = "presence";
+pub(crate) const EMOJI_KEY: &str = "emoji";
+pub(crate) const STICKER_KEY: &str = "sticker";
+pub(crate) const STAGE_INSTANCE_KEY: &str = "stage_instance";
+pub(crate) const SCHEDULED_EVENT_KEY: &str = "scheduled_event";
 
 pub(crate) const GUILD_KEYS: &str = "guild_keys";
 pub(crate) const CHANNEL_KEYS: &str = "channel_keys";
 pub(crate) const ROLE_KEYS: &str = "role_keys";
 pub(crate) const MEMBER_KEYS: &str = "member_keys";
+pub(crate) const USER_KEYS: &str = "user_keys";
 
-pub(crate) const OWNER_USER_ID: u64 = 219905108316520448;
+pub(crate) const GUILD_CHANNELS: &str = "guild_channels";
+pub(crate) const GUILD_ROLES: &str = "guild_roles";
+pub(crate) const GUILD_MEMBERS: &str = "guild_members";
+pub(crate) const GUILD_EMOJIS: &str = "guild_emojis";
+pub(crate) const GUILD_STICKERS: &str = "guild_stickers";
+pub(crate) const GUILD_STAGE_INSTANCES: &str = "guild_stage_instances";
+pub(crate) const GUILD_SCHEDULED_EVENTS: &str = "guild_scheduled_events";
+pub(crate) const USER_GUILDS: &str = "user_guilds";
+
+pub(crate) const EXPIRING_KEYS: &str = "expiring_keys";
+pub(crate) const THREAD_MEMBERS: &str = "thread_members";
+pub(crate) const THREAD_MEMBER_COUNT: &str = "thread_member_count";
+pub(crate) const ROLE_MEMBERS: &str = "role_members";
+pub(crate) const VOICE_MEMBERS: &str = "voice_members";
+pub(crate) const MESSAGES: &str = "messages";
+pub(crate) const MEMBER_COUNT: &str = "member_count";
@@ -1,13 +1,26 @@
-use deadpool_redis::redis::{AsyncCommands, FromRedisValue};
-use hashbrown::HashMap;
+use deadpool_redis::redis::{FromRedisValue, RedisResult, Value};
+use futures_util::{
+    future,
+    stream::{self, Stream, StreamExt, TryStreamExt},
+};
+use hashbrown::{HashMap, HashSet};
 use serde::de::DeserializeOwned;
-use twilight_model::id::{ChannelId, GuildId, RoleId, UserId};
+use twilight_model::{
+    channel::message::sticker::StickerId,
+    id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, StageId, UserId},
+};
 
 use crate::{
-    constants::GUILD_KEYS,
+    constants::{
+        GUILD_CHANNELS, GUILD_EMOJIS, GUILD_MEMBERS, GUILD_ROLES, GUILD_SCHEDULED_EVENTS,
+        GUILD_STAGE_INSTANCES, GUILD_STICKERS, MEMBER_COUNT, MESSAGES, ROLE_MEMBERS,
+        THREAD_MEMBERS, THREAD_MEMBER_COUNT, USER_GUILDS, VOICE_MEMBERS,
+    },
     model::{
-        CachedChannel, CachedCurrentUser, CachedGuild, CachedMember, CachedRole, IntoMemberIter,
-        RedisKey, SessionInfo,
+        filter_member_key, CachedChannel, CachedCurrentUser, CachedEmoji, CachedGuild,
+        CachedMember, CachedMessage, CachedPresence, CachedRole, CachedScheduledEvent,
+        CachedStageInstance, CachedSticker, CachedUser, CachedVoiceState, IntoMemberIter, RedisKey,
+        ScheduledEventId, SessionInfo,
     },
     CacheResult,
 };
@@ -16,20 +29,60 @@ use super::Cache;
 
 type FetchResult<T> = CacheResult<Option<T>>;
 
+/// Amount of index set entries requested per `SSCAN`
+const SCAN_COUNT: usize = 1000;
+
+/// Thread member counts are capped at this value
+const THREAD_MEMBER_COUNT_LIMIT: usize = 50;
+
 impl Cache {
     #[inline]
     pub async fn channel(&self, channel: ChannelId) -> FetchResult<CachedChannel> {
         self.get(channel.into()).await
     }
 
+    /// Retrieve all channels in a single request, skipping uncached ones
+    #[inline]
+    pub async fn channels(&self, channels: &[ChannelId]) -> CacheResult<Vec<CachedChannel>> {
+        self.get_many(channels.iter().copied().map(RedisKey::from))
+            .await
+    }
+
     #[inline]
     pub async fn current_user(&self) -> FetchResult<CachedCurrentUser> {
         self.get(RedisKey::BotUser).await
     }
 
     #[inline]
+    pub async fn emoji(&self, emoji: EmojiId) -> FetchResult<CachedEmoji> {
+        self.get(emoji.into()).await
+    }
+
+    /// Retrieve the guild together with its separately stored member count
     pub async fn guild(&self, guild: GuildId) -> FetchResult<CachedGuild> {
-        self.get(guild.into()).await
+        let keys = [
+            self.prefixed(&RedisKey::from(guild).to_string())
+                .into_owned(),
+            self.prefixed(&format!("{}:{}", MEMBER_COUNT, guild))
+                .into_owned(),
+        ];
+
+        let mut values = self.backend.get_many(&keys).await?.into_iter();
+
+        let mut guild: CachedGuild = match values.next().flatten() {
+            Some(value) => serde_cbor::from_slice(&value)?,
+            None => return Ok(None),
+        };
+
+        // The counter starts from zero if a leave is handled before the guild was cached
+        guild.member_count = values
+            .next()
+            .flatten()
+            .and_then(|value| String::from_utf8(value).ok())
+            .and_then(|value| value.parse::<i64>().ok())
+            .map(|count| count.max(0) as u64);
+
+        Ok(Some(guild))
     }
 
     #[inline]
@@ -37,24 +90,363 @@ impl Cache {
         self.get((guild, user).into()).await
     }
 
+    /// Retrieve all members of the guild in a single request, skipping uncached ones
+    #[inline]
+    pub async fn members_of(
+        &self,
+        guild: GuildId,
+        users: &[UserId],
+    ) -> CacheResult<Vec<CachedMember>> {
+        self.get_many(users.iter().map(|&user| RedisKey::from((guild, user))))
+            .await
+    }
+
+    /// Check whether the user joined the thread
     #[inline]
+    pub async fn is_thread_member(&self, thread: ChannelId, user: UserId) -> CacheResult<bool> {
+        let key = format!("{}:{}", THREAD_MEMBERS, thread);
+
+        self.backend
+            .set_contains(&self.prefixed(&key), &user.to_string())
+            .await
+    }
+
+    /// Check whether the user joined the thread, `None` if that's unknown because
+    /// not all members of the thread are cached.
+    ///
+    /// Members that joined before the thread was cached only show up once they're part
+    /// of a thread members update which requires the `GUILD_MEMBERS` intent.
+    pub async fn thread_membership(
+        &self,
+        thread: ChannelId,
+        user: UserId,
+    ) -> CacheResult<Option<bool>> {
+        if self.is_thread_member(thread, user).await? {
+            return Ok(Some(true));
+        }
+
+        let key = format!("{}:{}", THREAD_MEMBER_COUNT, thread);
+
+        let count = match self.backend.get(&self.prefixed(&key)).await? {
+            Some(value) => String::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok()),
+            None => None,
+        };
+
+        // Discord stops counting thread members at 50
+        let count = match count {
+            Some(count) if count < THREAD_MEMBER_COUNT_LIMIT => count,
+            _ => return Ok(None),
+        };
+
+        let key = format!("{}:{}", THREAD_MEMBERS, thread);
+        let cached = self.backend.set_len(&self.prefixed(&key)).await?;
+
+        Ok(Some(false).filter(|_| cached == count))
+    }
+
+    /// Retrieve the ids of all cached members of the guild, skipping expired ones
     pub async fn members(&self, guild: GuildId) -> CacheResult<IntoMemberIter> {
-        let key = format!("{}:{}", GUILD_KEYS, guild);
-        let keys = self.get_members(key).await?;
+        self.prune_if_due().await?;
+
+        // Expired keys are read first so that keys pruned in between are missing from both
+        let expired = self.expired_members(guild).await?;
+
+        let key = format!("{}:{}", GUILD_MEMBERS, guild);
+
+        let keys = self
+            .get_members::<RedisKey>(key)
+            .await?
+            .into_iter()
+            .filter(|key| !matches!(key, RedisKey::Member { user, .. } if expired.contains(user)))
+            .collect();
 
         Ok(IntoMemberIter::new(keys))
     }
 
+    /// Lazily page through the members of the guild.
+    ///
+    /// Unlike [`Cache::members`] this does not load the guild's whole index set
+    /// at once so it should be preferred for huge guilds.
+    /// Each member is yielded once.
+    pub async fn member_stream(
+        &self,
+        guild: GuildId,
+    ) -> CacheResult<impl Stream<Item = CacheResult<UserId>> + '_> {
+        self.prune_if_due().await?;
+
+        let expired = self.expired_members(guild).await?;
+        let key = format!("{}:{}", GUILD_MEMBERS, guild);
+
+        let stream = self.scan_members(key).flat_map(move |page| {
+            let users: Vec<_> = match page {
+                Ok(keys) => keys
+                    .into_iter()
+                    .filter_map(filter_member_key)
+                    .filter(|user| !expired.contains(user))
+                    .map(Ok)
+                    .collect(),
+                Err(err) => vec![Err(err)],
+            };
+
+            stream::iter(users)
+        });
+
+        Ok(stream)
+    }
+
+    /// Lazily page through the members of the guild, retrieving the data of
+    /// each page's members in a single request
+    pub async fn member_chunks(
+        &self,
+        guild: GuildId,
+    ) -> CacheResult<impl Stream<Item = CacheResult<Vec<CachedMember>>> + '_> {
+        self.prune_if_due().await?;
+
+        let key = format!("{}:{}", GUILD_MEMBERS, guild);
+
+        let stream = self
+            .scan_members::<RedisKey>(key)
+            .and_then(move |keys| self.get_many(keys))
+            .try_filter(|members| future::ready(!members.is_empty()));
+
+        Ok(stream)
+    }
+
+    /// Retrieve all cached members of the guild that have the role
+    pub async fn members_with_role(
+        &self,
+        guild: GuildId,
+        role: RoleId,
+    ) -> CacheResult<Vec<CachedMember>> {
+        let key = format!("{}:{}", ROLE_MEMBERS, role);
+
+        let keys = self
+            .get_members::<String>(key)
+            .await?
+            .into_iter()
+            .filter_map(|user| user.parse().ok().map(UserId))
+            .map(|user| RedisKey::from((guild, user)));
+
+        // Expired members and outdated entries of the index are skipped
+        let members = self
+            .get_many::<_, CachedMember>(keys)
+            .await?
+            .into_iter()
+            .filter(|member| member.roles.contains(&role))
+            .collect();
+
+        Ok(members)
+    }
+
+    /// Retrieve all cached channels of the guild
+    pub async fn guild_channels(&self, guild: GuildId) -> CacheResult<Vec<CachedChannel>> {
+        let key = format!("{}:{}", GUILD_CHANNELS, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+
+        self.get_many(keys).await
+    }
+
+    /// Retrieve all cached members of the guild
+    pub async fn guild_members(&self, guild: GuildId) -> CacheResult<Vec<CachedMember>> {
+        self.prune_if_due().await?;
+
+        let key = format!("{}:{}", GUILD_MEMBERS, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+
+        self.get_many(keys).await
+    }
+
+    /// Retrieve all cached emojis of the guild
+    pub async fn guild_emojis(&self, guild: GuildId) -> CacheResult<Vec<CachedEmoji>> {
+        let key = format!("{}:{}", GUILD_EMOJIS, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+
+        self.get_many(keys).await
+    }
+
+    /// Retrieve all cached scheduled events of the guild, the earliest first
+    pub async fn guild_scheduled_events(
+        &self,
+        guild: GuildId,
+    ) -> CacheResult<Vec<CachedScheduledEvent>> {
+        let key = format!("{}:{}", GUILD_SCHEDULED_EVENTS, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+        let mut events: Vec<CachedScheduledEvent> = self.get_many(keys).await?;
+        events.sort_unstable_by_key(|event| (event.scheduled_start_time.as_micros(), event.id));
+
+        Ok(events)
+    }
+
+    /// Retrieve all cached stage instances of the guild
+    pub async fn guild_stage_instances(
+        &self,
+        guild: GuildId,
+    ) -> CacheResult<Vec<CachedStageInstance>> {
+        let key = format!("{}:{}", GUILD_STAGE_INSTANCES, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+
+        self.get_many(keys).await
+    }
+
+    /// Retrieve all cached stickers of the guild
+    pub async fn guild_stickers(&self, guild: GuildId) -> CacheResult<Vec<CachedSticker>> {
+        let key = format!("{}:{}", GUILD_STICKERS, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+
+        self.get_many(keys).await
+    }
+
+    /// Retrieve all cached roles of the guild
+    pub async fn guild_roles(&self, guild: GuildId) -> CacheResult<Vec<CachedRole>> {
+        let key = format!("{}:{}", GUILD_ROLES, guild);
+        let keys = self.get_members::<RedisKey>(key).await?;
+
+        self.get_many(keys).await
+    }
+
+    /// Retrieve the most recent cached messages of the channel, newest first.
+    ///
+    /// Only available if [`CacheConfig::message_cache_size`](crate::model::CacheConfig::message_cache_size)
+    /// is set.
+    pub async fn recent_messages(
+        &self,
+        channel: ChannelId,
+        limit: usize,
+    ) -> CacheResult<Vec<CachedMessage>> {
+        let key = format!("{}:{}", MESSAGES, channel);
+
+        self.backend
+            .list_front(&self.prefixed(&key), limit)
+            .await?
+            .into_iter()
+            .map(|value| serde_cbor::from_slice(&value).map_err(Into::into))
+            .collect()
+    }
+
+    /// Retrieve the message if it is among the channel's recent messages.
+    ///
+    /// Deleted messages are removed from the cache so to keep their content,
+    /// retrieve them before passing the delete event to [`Cache::update`].
+    pub async fn message(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+    ) -> FetchResult<CachedMessage> {
+        let messages = self.cached_messages(channel).await?;
+
+        Ok(messages.into_iter().find(|cached| cached.id == message))
+    }
+
+    pub(crate) async fn cached_messages(
+        &self,
+        channel: ChannelId,
+    ) -> CacheResult<Vec<CachedMessage>> {
+        self.recent_messages(channel, usize::MAX).await
+    }
+
+    /// Only available if [`CacheConfig::cache_presences`](crate::model::CacheConfig::cache_presences)
+    /// is enabled
+    #[inline]
+    pub async fn presence(&self, guild: GuildId, user: UserId) -> FetchResult<CachedPresence> {
+        self.get(RedisKey::Presence { guild, user }).await
+    }
+
     #[inline]
     pub async fn role(&self, role: RoleId) -> FetchResult<CachedRole> {
         self.get(role.into()).await
     }
 
+    /// Retrieve all roles in a single request, skipping uncached ones
+    #[inline]
+    pub async fn roles(&self, roles: &[RoleId]) -> CacheResult<Vec<CachedRole>> {
+        self.get_many(roles.iter().copied().map(RedisKey::from))
+            .await
+    }
+
     #[inline]
     pub async fn shards(&self) -> FetchResult<u64> {
         self.get(RedisKey::Shards).await
     }
 
+    #[inline]
+    pub async fn scheduled_event(
+        &self,
+        event: ScheduledEventId,
+    ) -> FetchResult<CachedScheduledEvent> {
+        self.get(event.into()).await
+    }
+
+    #[inline]
+    pub async fn stage_instance(&self, stage: StageId) -> FetchResult<CachedStageInstance> {
+        self.get(stage.into()).await
+    }
+
+    #[inline]
+    pub async fn sticker(&self, sticker: StickerId) -> FetchResult<CachedSticker> {
+        self.get(sticker.into()).await
+    }
+
+    /// Retrieve the user, shared across all guilds the user is cached in
+    #[inline]
+    pub async fn user(&self, user: UserId) -> FetchResult<CachedUser> {
+        self.get(user.into()).await
+    }
+
+    /// Retrieve all guilds in which the user is cached as member
+    pub async fn user_guilds(&self, user: UserId) -> CacheResult<Vec<GuildId>> {
+        self.prune_if_due().await?;
+
+        let key = format!("{}:{}", USER_GUILDS, user);
+
+        let guilds: Vec<_> = self
+            .get_members::<RedisKey>(key)
+            .await?
+            .into_iter()
+            .filter_map(|key| match key {
+                RedisKey::Guild { guild } => Some(guild),
+                _ => None,
+            })
+            .collect();
+
+        // Guilds in which the member expired but was not yet pruned are skipped
+        let keys: Vec<_> = guilds
+            .iter()
+            .map(|&guild| RedisKey::from((guild, user)).to_string())
+            .map(|key| self.prefixed(&key).into_owned())
+            .collect();
+
+        let values = self.backend.get_many(&keys).await?;
+
+        let guilds = guilds
+            .into_iter()
+            .zip(values)
+            .filter_map(|(guild, value)| value.map(|_| guild))
+            .collect();
+
+        Ok(guilds)
+    }
+
+    #[inline]
+    pub async fn voice_state(&self, guild: GuildId, user: UserId) -> FetchResult<CachedVoiceState> {
+        self.get(RedisKey::VoiceState { guild, user }).await
+    }
+
+    /// Retrieve all users that are currently in the voice channel
+    pub async fn voice_channel_members(&self, channel: ChannelId) -> CacheResult<Vec<UserId>> {
+        let key = format!("{}:{}", VOICE_MEMBERS, channel);
+
+        let users = self
+            .get_members::<String>(key)
+            .await?
+            .into_iter()
+            .filter_map(|user| user.parse().ok().map(UserId))
+            .collect();
+
+        Ok(users)
+    }
+
     #[inline]
     pub async fn sessions(&self) -> FetchResult<HashMap<u64, SessionInfo>> {
         self.get(RedisKey::Sessions).await
@@ -64,19 +456,110 @@ impl Cache {
     where
         T: DeserializeOwned,
     {
-        let mut conn = self.redis.get().await?;
-        let res: Option<Vec<u8>> = conn.get(key).await?;
+        let res = self.backend.get(&self.prefixed(&key.to_string())).await?;
         let opt = res.map(|value| serde_cbor::from_slice(&value));
 
         Ok(opt.transpose()?)
     }
 
+    /// Retrieve the values of all keys in a single request, skipping missing ones
+    async fn get_many<I, T>(&self, keys: I) -> CacheResult<Vec<T>>
+    where
+        I: IntoIterator<Item = RedisKey>,
+        T: DeserializeOwned,
+    {
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|key| self.prefixed(&key.to_string()).into_owned())
+            .collect();
+
+        self.backend
+            .get_many(&keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|value| serde_cbor::from_slice(&value).map_err(Into::into))
+            .collect()
+    }
+
     pub(crate) async fn get_members<T>(&self, key: String) -> CacheResult<Vec<T>>
     where
         T: FromRedisValue,
     {
-        let mut conn = self.redis.get().await?;
+        let members = self.backend.set_members(&self.prefixed(&key)).await?;
+
+        parse_members(members)
+    }
+
+    /// Page through the set through `SSCAN` until the cursor wraps around.
+    ///
+    /// `SSCAN` may return an element more than once so elements
+    /// of previous pages are filtered out.
+    fn scan_members<T>(&self, key: String) -> impl Stream<Item = CacheResult<Vec<T>>> + '_
+    where
+        T: FromRedisValue,
+    {
+        let key = self.prefixed(&key).into_owned();
+
+        stream::unfold(Some((key, 0, HashSet::new())), move |state| async move {
+            let (key, cursor, mut seen) = state?;
+
+            match self.backend.set_scan(&key, cursor, SCAN_COUNT).await {
+                Ok((next, members)) => {
+                    let members = members
+                        .into_iter()
+                        .filter(|member| seen.insert(member.clone()))
+                        .collect();
+
+                    let state = Some(next)
+                        .filter(|&next| next != 0)
+                        .map(|next| (key, next, seen));
 
-        Ok(conn.smembers(key).await?)
+                    Some((parse_members(members), state))
+                }
+                Err(err) => Some((Err(err), None)),
+            }
+        })
     }
+
+    /// Retrieve the users of the guild whose member data expired
+    /// but that are not yet pruned from the index sets
+    async fn expired_members(&self, guild: GuildId) -> CacheResult<HashSet<UserId>> {
+        let expired = self
+            .expired_keys()
+            .await?
+            .into_iter()
+            .filter_map(|key| match key {
+                RedisKey::Member { guild: g, user } if g == guild => Some(user),
+                _ => None,
+            })
+            .collect();
+
+        Ok(expired)
+    }
+
+    pub(crate) async fn get_sorted_members<T>(
+        &self,
+        key: &str,
+        max_score: u64,
+    ) -> CacheResult<Vec<T>>
+    where
+        T: FromRedisValue,
+    {
+        let members = self
+            .backend
+            .sorted_set_range(&self.prefixed(key), max_score)
+            .await?;
+
+        parse_members(members)
+    }
+}
+
+fn parse_members<T: FromRedisValue>(members: Vec<Vec<u8>>) -> CacheResult<Vec<T>> {
+    let members = members
+        .into_iter()
+        .map(|member| T::from_redis_value(&Value::Data(member)))
+        .collect::<RedisResult<_>>()?;
+
+    Ok(members)
 }
@@ -1,8 +1,11 @@
 #![deny(clippy::all, nonstandard_style, rust_2018_idioms, unused, warnings)]
 
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display, sync::atomic::AtomicU64};
 
-use deadpool_redis::{Config, Pool, PoolConfig};
+use deadpool_redis::{
+    redis::{ConnectionAddr, ConnectionInfo, IntoConnectionInfo, RedisConnectionInfo},
+    CreatePoolError, Manager, Pool, PoolConfig, Runtime, Timeouts,
+};
 
 mod constants;
 mod error;
@@ -10,17 +13,24 @@ mod fetch;
 mod store;
 mod util;
 
+#[cfg(test)]
+mod test_util;
+
+pub mod backend;
 pub mod model;
 
-use model::CacheConfig;
+use backend::{CacheBackend, RedisBackend};
+use model::{CacheConfig, RedisAddr, RedisConfig};
 
 pub use error::{CacheError, CacheResult};
 use twilight_model::id::UserId;
 
 pub struct Cache {
-    redis: Pool,
+    backend: Box<dyn CacheBackend>,
     config: CacheConfig,
     bot_id: UserId,
+    /// Unix timestamp of the last time expired members were pruned
+    last_prune: AtomicU64,
 }
 
 impl Cache {
@@ -34,18 +44,82 @@ impl Cache {
         bot_id: UserId,
         config: CacheConfig,
     ) -> CacheResult<Self> {
-        let redis_config = Config {
-            url: Some(format!("redis://{}:{}", host, port)),
-            connection: None,
-            pool: Some(PoolConfig::new(4)),
+        let redis = RedisConfig::from_url(format!("redis://{}:{}", host, port));
+
+        Self::with_redis(redis, bot_id, config)
+    }
+
+    /// Create a cache on top of redis with full control over the connection
+    pub fn with_redis(
+        redis: RedisConfig,
+        bot_id: UserId,
+        config: CacheConfig,
+    ) -> CacheResult<Self> {
+        let connection = match redis.url {
+            Some(url) => url.as_str().into_connection_info()?,
+            None => {
+                let addr = match redis.addr {
+                    RedisAddr::Tcp { host, port } => ConnectionAddr::Tcp(host, port),
+                    RedisAddr::Tls {
+                        host,
+                        port,
+                        insecure,
+                    } => ConnectionAddr::TcpTls {
+                        host,
+                        port,
+                        insecure,
+                    },
+                    RedisAddr::Unix(path) => ConnectionAddr::Unix(path),
+                };
+
+                let redis = RedisConnectionInfo {
+                    db: redis.db,
+                    username: redis.username,
+                    password: redis.password,
+                };
+
+                ConnectionInfo { addr, redis }
+            }
         };
 
-        let redis = redis_config.create_pool(None)?;
+        let pool_config = PoolConfig {
+            max_size: redis.pool_size,
+            timeouts: Timeouts {
+                wait: redis.wait_timeout,
+                create: redis.create_timeout,
+                recycle: redis.recycle_timeout,
+            },
+        };
+
+        let redis = Pool::builder(Manager::new(connection)?)
+            .config(pool_config)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .map_err(CreatePoolError::Build)?;
+
+        Ok(Self::with_backend(RedisBackend::new(redis), bot_id, config))
+    }
 
-        Ok(Self {
-            redis,
+    /// Create a cache on top of a custom backend such as
+    /// [`MemoryBackend`](backend::MemoryBackend)
+    pub fn with_backend(
+        backend: impl CacheBackend + 'static,
+        bot_id: UserId,
+        config: CacheConfig,
+    ) -> Self {
+        Self {
+            backend: Box::new(backend),
             config,
             bot_id,
-        })
+            last_prune: AtomicU64::new(0),
+        }
+    }
+
+    /// Prepend the configured key prefix
+    pub(crate) fn prefixed<'k>(&self, key: &'k str) -> Cow<'k, str> {
+        match self.config.key_prefix {
+            Some(ref prefix) => Cow::Owned(format!("{}:{}", prefix, key)),
+            None => Cow::Borrowed(key),
+        }
     }
 }
@@ -4,15 +4,19 @@ use deadpool_redis::redis::{
     ErrorKind, FromRedisValue, RedisError, RedisResult, RedisWrite, ToRedisArgs, Value,
 };
 use twilight_model::{
+    channel::message::sticker::StickerId,
     guild::Member,
-    id::{ChannelId, GuildId, RoleId, UserId},
+    id::{ChannelId, EmojiId, GuildId, RoleId, StageId, UserId},
+    user::User,
 };
 
 use crate::constants::{
-    BOT_USER_KEY, CHANNEL_KEY, GUILD_KEY, MEMBER_KEY, ROLE_KEY, SESSIONS_KEY, SHARDS_KEY,
+    BOT_USER_KEY, CHANNEL_KEY, EMOJI_KEY, GUILD_KEY, MEMBER_KEY, PRESENCE_KEY, ROLE_KEY,
+    SCHEDULED_EVENT_KEY, SESSIONS_KEY, SHARDS_KEY, STAGE_INSTANCE_KEY, STICKER_KEY, USER_KEY,
+    VOICE_STATE_KEY,
 };
 
-use super::{BasicGuildChannel, CachedChannel, MemberWrapper};
+use super::{BasicGuildChannel, CachedChannel, MemberWrapper, PresenceWrapper, ScheduledEventId};
 
 #[derive(Copy, Clone, Debug)]
 pub enum RedisKey {
@@ -34,14 +38,72 @@ pub enum RedisKey {
     },
     Sessions,
     Shards,
+    User {
+        user: UserId,
+    },
+    VoiceState {
+        guild: GuildId,
+        user: UserId,
+    },
+    Presence {
+        guild: GuildId,
+        user: UserId,
+    },
+    Emoji {
+        guild: Option<GuildId>,
+        emoji: EmojiId,
+    },
+    Sticker {
+        guild: Option<GuildId>,
+        sticker: StickerId,
+    },
+    StageInstance {
+        guild: Option<GuildId>,
+        stage: StageId,
+    },
+    ScheduledEvent {
+        guild: Option<GuildId>,
+        event: ScheduledEventId,
+    },
 }
 
 impl RedisKey {
     pub(crate) fn user_id(&self) -> Option<UserId> {
-        if let RedisKey::Member { user, .. } = self {
-            Some(*user)
-        } else {
-            None
+        match self {
+            RedisKey::Member { user, .. } | RedisKey::User { user } => Some(*user),
+            _ => None,
+        }
+    }
+
+    /// Channel, role, emoji, sticker, stage instance and scheduled event keys don't include their guild in their string
+    /// representation so this restores it for keys that were read from a guild's index set.
+    pub(crate) fn with_guild(self, guild: GuildId) -> Self {
+        match self {
+            Self::Channel { channel, .. } => Self::Channel {
+                guild: Some(guild),
+                channel,
+            },
+            Self::Role { role, .. } => Self::Role {
+                guild: Some(guild),
+                role,
+            },
+            Self::Emoji { emoji, .. } => Self::Emoji {
+                guild: Some(guild),
+                emoji,
+            },
+            Self::Sticker { sticker, .. } => Self::Sticker {
+                guild: Some(guild),
+                sticker,
+            },
+            Self::StageInstance { stage, .. } => Self::StageInstance {
+                guild: Some(guild),
+                stage,
+            },
+            Self::ScheduledEvent { event, .. } => Self::ScheduledEvent {
+                guild: Some(guild),
+                event,
+            },
+            key => key,
         }
     }
 }
@@ -56,6 +118,17 @@ impl fmt::Display for RedisKey {
             Self::Role { role, .. } => write!(f, "{}:{}", ROLE_KEY, role),
             Self::Sessions => f.write_str(SESSIONS_KEY),
             Self::Shards => f.write_str(SHARDS_KEY),
+            Self::User { user } => write!(f, "{}:{}", USER_KEY, user),
+            Self::VoiceState { guild, user } => {
+                write!(f, "{}:{}:{}", VOICE_STATE_KEY, guild, user)
+            }
+            Self::Presence { guild, user } => write!(f, "{}:{}:{}", PRESENCE_KEY, guild, user),
+            Self::Emoji { emoji, .. } => write!(f, "{}:{}", EMOJI_KEY, emoji),
+            Self::Sticker { sticker, .. } => write!(f, "{}:{}", STICKER_KEY, sticker),
+            Self::StageInstance { stage, .. } => write!(f, "{}:{}", STAGE_INSTANCE_KEY, stage),
+            Self::ScheduledEvent { event, .. } => {
+                write!(f, "{}:{}", SCHEDULED_EVENT_KEY, event)
+            }
         }
     }
 }
@@ -111,6 +184,69 @@ impl From<(GuildId, RoleId)> for RedisKey {
     }
 }
 
+impl From<(GuildId, EmojiId)> for RedisKey {
+    fn from((guild, emoji): (GuildId, EmojiId)) -> Self {
+        Self::Emoji {
+            guild: Some(guild),
+            emoji,
+        }
+    }
+}
+
+impl From<EmojiId> for RedisKey {
+    fn from(emoji: EmojiId) -> Self {
+        Self::Emoji { guild: None, emoji }
+    }
+}
+
+impl From<(GuildId, StickerId)> for RedisKey {
+    fn from((guild, sticker): (GuildId, StickerId)) -> Self {
+        Self::Sticker {
+            guild: Some(guild),
+            sticker,
+        }
+    }
+}
+
+impl From<StickerId> for RedisKey {
+    fn from(sticker: StickerId) -> Self {
+        Self::Sticker {
+            guild: None,
+            sticker,
+        }
+    }
+}
+
+impl From<(GuildId, StageId)> for RedisKey {
+    fn from((guild, stage): (GuildId, StageId)) -> Self {
+        Self::StageInstance {
+            guild: Some(guild),
+            stage,
+        }
+    }
+}
+
+impl From<StageId> for RedisKey {
+    fn from(stage: StageId) -> Self {
+        Self::StageInstance { guild: None, stage }
+    }
+}
+
+impl From<(GuildId, ScheduledEventId)> for RedisKey {
+    fn from((guild, event): (GuildId, ScheduledEventId)) -> Self {
+        Self::ScheduledEvent {
+            guild: Some(guild),
+            event,
+        }
+    }
+}
+
+impl From<ScheduledEventId> for RedisKey {
+    fn from(event: ScheduledEventId) -> Self {
+        Self::ScheduledEvent { guild: None, event }
+    }
+}
+
 impl From<RoleId> for RedisKey {
     fn from(role: RoleId) -> Self {
         Self::Role { guild: None, role }
@@ -132,6 +268,27 @@ impl From<GuildId> for RedisKey {
     }
 }
 
+impl From<UserId> for RedisKey {
+    fn from(user: UserId) -> Self {
+        Self::User { user }
+    }
+}
+
+impl From<&User> for RedisKey {
+    fn from(user: &User) -> Self {
+        Self::User { user: user.id }
+    }
+}
+
+impl From<&PresenceWrapper<'_>> for RedisKey {
+    fn from(presence: &PresenceWrapper<'_>) -> Self {
+        Self::Presence {
+            guild: presence.guild_id(),
+            user: presence.user_id(),
+        }
+    }
+}
+
 impl From<(GuildId, UserId)> for RedisKey {
     fn from((guild, user): (GuildId, UserId)) -> Self {
         Self::Member { guild, user }
@@ -202,6 +359,92 @@ impl FromRedisValue for RedisKey {
                         return Ok(RedisKey::Role { guild: None, role });
                     }
                 }
+                Some(USER_KEY) => {
+                    let parse = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(UserId))
+                        .filter(|_| split.next().is_none());
+
+                    if let Some(Ok(user)) = parse {
+                        return Ok(RedisKey::User { user });
+                    }
+                }
+                Some(VOICE_STATE_KEY) => {
+                    let guild = split.next().map(str::parse).map(|res| res.map(GuildId));
+
+                    let user = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(UserId))
+                        .filter(|_| split.next().is_none());
+
+                    if let (Some(Ok(guild)), Some(Ok(user))) = (guild, user) {
+                        return Ok(RedisKey::VoiceState { guild, user });
+                    }
+                }
+                Some(EMOJI_KEY) => {
+                    let parse = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(EmojiId))
+                        .filter(|_| split.next().is_none());
+
+                    if let Some(Ok(emoji)) = parse {
+                        return Ok(RedisKey::Emoji { guild: None, emoji });
+                    }
+                }
+                Some(STICKER_KEY) => {
+                    let parse = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(StickerId))
+                        .filter(|_| split.next().is_none());
+
+                    if let Some(Ok(sticker)) = parse {
+                        let key = RedisKey::Sticker {
+                            guild: None,
+                            sticker,
+                        };
+
+                        return Ok(key);
+                    }
+                }
+                Some(STAGE_INSTANCE_KEY) => {
+                    let parse = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(StageId))
+                        .filter(|_| split.next().is_none());
+
+                    if let Some(Ok(stage)) = parse {
+                        return Ok(RedisKey::StageInstance { guild: None, stage });
+                    }
+                }
+                Some(SCHEDULED_EVENT_KEY) => {
+                    let parse = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(ScheduledEventId))
+                        .filter(|_| split.next().is_none());
+
+                    if let Some(Ok(event)) = parse {
+                        return Ok(RedisKey::ScheduledEvent { guild: None, event });
+                    }
+                }
+                Some(PRESENCE_KEY) => {
+                    let guild = split.next().map(str::parse).map(|res| res.map(GuildId));
+
+                    let user = split
+                        .next()
+                        .map(str::parse)
+                        .map(|res| res.map(UserId))
+                        .filter(|_| split.next().is_none());
+
+                    if let (Some(Ok(guild)), Some(Ok(user))) = (guild, user) {
+                        return Ok(RedisKey::Presence { guild, user });
+                    }
+                }
                 _ => {}
             }
 
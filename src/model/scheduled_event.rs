@@ -0,0 +1,156 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    num::NonZeroU64,
+};
+
+use serde::{
+    de::{Deserializer, Error as DeError, Visitor},
+    Deserialize, Serialize, Serializer,
+};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use twilight_model::{
+    datetime::Timestamp,
+    id::{ChannelId, GuildId, UserId},
+};
+
+/// Guild scheduled event as sent by discord in `GUILD_SCHEDULED_EVENT_*` dispatches
+/// and the `guild_scheduled_events` of `GUILD_CREATE`.
+///
+/// The twilight version in use does not model scheduled events yet
+/// so they have to be deserialized from the raw payload.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct ScheduledEvent {
+    #[serde(default)]
+    pub channel_id: Option<ChannelId>,
+    #[serde(default)]
+    pub creator_id: Option<UserId>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub entity_metadata: Option<ScheduledEventMetadata>,
+    pub entity_type: ScheduledEventEntityType,
+    pub guild_id: GuildId,
+    pub id: ScheduledEventId,
+    #[serde(default)]
+    pub image: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub scheduled_end_time: Option<Timestamp>,
+    pub scheduled_start_time: Timestamp,
+    pub status: ScheduledEventStatus,
+    #[serde(default)]
+    pub user_count: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct ScheduledEventMetadata {
+    /// Location of external events
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize_repr, Eq, Hash, PartialEq, Serialize_repr)]
+#[repr(u8)]
+pub enum ScheduledEventEntityType {
+    StageInstance = 1,
+    Voice = 2,
+    External = 3,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize_repr, Eq, Hash, PartialEq, Serialize_repr)]
+#[repr(u8)]
+pub enum ScheduledEventStatus {
+    Scheduled = 1,
+    Active = 2,
+    Completed = 3,
+    Cancelled = 4,
+}
+
+/// Id of a [`ScheduledEvent`], (de)serialized as string like twilight's ids
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ScheduledEventId(pub NonZeroU64);
+
+impl ScheduledEventId {
+    pub const fn new(n: u64) -> Option<Self> {
+        match NonZeroU64::new(n) {
+            Some(n) => Some(Self(n)),
+            None => None,
+        }
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0.get()
+    }
+}
+
+impl Display for ScheduledEventId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for ScheduledEventId {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScheduledEventId {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl<'de> Visitor<'de> for IdVisitor {
+            type Value = ScheduledEventId;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+                f.write_str("a non-zero snowflake as string or integer")
+            }
+
+            fn visit_u64<E: DeError>(self, v: u64) -> Result<Self::Value, E> {
+                ScheduledEventId::new(v).ok_or_else(|| E::custom("id must be non-zero"))
+            }
+
+            fn visit_str<E: DeError>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map(ScheduledEventId).map_err(E::custom)
+            }
+        }
+
+        d.deserialize_any(IdVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScheduledEvent, ScheduledEventEntityType, ScheduledEventId, ScheduledEventStatus};
+
+    #[test]
+    fn deserialize_dispatch_payload() {
+        let payload = r#"{
+            "id": "941322436398923847",
+            "guild_id": "197038439483310086",
+            "channel_id": null,
+            "creator_id": "81388395867156480",
+            "name": "osu! tournament finals",
+            "description": "Streamed on twitch",
+            "scheduled_start_time": "2022-02-12T18:00:00+00:00",
+            "scheduled_end_time": "2022-02-12T21:00:00+00:00",
+            "privacy_level": 2,
+            "status": 1,
+            "entity_type": 3,
+            "entity_id": null,
+            "entity_metadata": { "location": "https://twitch.tv/osulive" },
+            "image": null
+        }"#;
+
+        let event: ScheduledEvent = serde_json::from_str(payload).unwrap();
+
+        assert_eq!(event.id, ScheduledEventId::new(941322436398923847).unwrap());
+        assert_eq!(event.channel_id, None);
+        assert_eq!(event.entity_type, ScheduledEventEntityType::External);
+        assert_eq!(event.status, ScheduledEventStatus::Scheduled);
+        assert_eq!(event.user_count, None);
+
+        let location = event.entity_metadata.and_then(|metadata| metadata.location);
+        assert_eq!(location.as_deref(), Some("https://twitch.tv/osulive"));
+    }
+}
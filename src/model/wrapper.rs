@@ -1,15 +1,23 @@
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use twilight_model::{
     channel::{
-        thread::{PrivateThread, PublicThread},
-        GuildChannel, TextChannel,
+        message::sticker::Sticker,
+        stage_instance::StageInstance,
+        thread::{NewsThread, PrivateThread, PublicThread},
+        CategoryChannel, ChannelType, GuildChannel, Message, TextChannel, VoiceChannel,
     },
-    gateway::payload::incoming::MemberUpdate,
-    guild::{Guild, Member, PartialGuild, PartialMember, Role},
-    id::{ChannelId, GuildId},
+    gateway::{
+        payload::incoming::{MemberUpdate, PresenceUpdate},
+        presence::{Activity, Presence, Status},
+    },
+    guild::{Emoji, Guild, Member, PartialGuild, PartialMember, Role, RoleTags},
+    id::{ChannelId, GuildId, RoleId, UserId},
     user::{CurrentUser, User},
+    voice::VoiceState,
 };
 
+use super::ScheduledEvent;
+
 pub struct GuildWrapper<'g>(pub &'g Guild);
 
 impl<'g> From<&'g Guild> for GuildWrapper<'g> {
@@ -20,7 +28,13 @@ impl<'g> From<&'g Guild> for GuildWrapper<'g> {
 
 impl<'g> Serialize for GuildWrapper<'g> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let len = 3 + self.0.icon.is_some() as usize;
+        let len = 5
+            + self.0.icon.is_some() as usize
+            + !self.0.features.is_empty() as usize
+            + self.0.banner.is_some() as usize
+            + self.0.system_channel_id.is_some() as usize
+            + self.0.vanity_url_code.is_some() as usize;
+
         let mut guild = s.serialize_struct("CachedGuild", len)?;
 
         if let Some(ref icon) = self.0.icon {
@@ -31,6 +45,25 @@ impl<'g> Serialize for GuildWrapper<'g> {
         guild.serialize_field("c", &self.0.name)?;
         guild.serialize_field("d", &self.0.owner_id)?;
 
+        if !self.0.features.is_empty() {
+            guild.serialize_field("f", &self.0.features)?;
+        }
+
+        guild.serialize_field("g", &self.0.preferred_locale)?;
+        guild.serialize_field("h", &self.0.premium_tier)?;
+
+        if let Some(ref banner) = self.0.banner {
+            guild.serialize_field("i", banner)?;
+        }
+
+        if let Some(ref system_channel_id) = self.0.system_channel_id {
+            guild.serialize_field("j", system_channel_id)?;
+        }
+
+        if let Some(ref vanity_url_code) = self.0.vanity_url_code {
+            guild.serialize_field("k", vanity_url_code)?;
+        }
+
         guild.end()
     }
 }
@@ -45,7 +78,13 @@ impl<'g> From<&'g PartialGuild> for PartialGuildWrapper<'g> {
 
 impl<'g> Serialize for PartialGuildWrapper<'g> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let len = 3 + self.0.icon.is_some() as usize;
+        let len = 5
+            + self.0.icon.is_some() as usize
+            + !self.0.features.is_empty() as usize
+            + self.0.banner.is_some() as usize
+            + self.0.system_channel_id.is_some() as usize
+            + self.0.vanity_url_code.is_some() as usize;
+
         let mut guild = s.serialize_struct("CachedGuild", len)?;
 
         if let Some(ref icon) = self.0.icon {
@@ -56,10 +95,31 @@ impl<'g> Serialize for PartialGuildWrapper<'g> {
         guild.serialize_field("c", &self.0.name)?;
         guild.serialize_field("d", &self.0.owner_id)?;
 
+        if !self.0.features.is_empty() {
+            guild.serialize_field("f", &self.0.features)?;
+        }
+
+        guild.serialize_field("g", &self.0.preferred_locale)?;
+        guild.serialize_field("h", &self.0.premium_tier)?;
+
+        if let Some(ref banner) = self.0.banner {
+            guild.serialize_field("i", banner)?;
+        }
+
+        if let Some(ref system_channel_id) = self.0.system_channel_id {
+            guild.serialize_field("j", system_channel_id)?;
+        }
+
+        if let Some(ref vanity_url_code) = self.0.vanity_url_code {
+            guild.serialize_field("k", vanity_url_code)?;
+        }
+
         guild.end()
     }
 }
 
+// Unused while Ready and UserUpdate are handled in bot context
+#[allow(dead_code)]
 pub struct CurrentUserWrapper<'u>(pub &'u CurrentUser);
 
 impl<'u> From<&'u CurrentUser> for CurrentUserWrapper<'u> {
@@ -95,17 +155,92 @@ impl<'r> From<&'r Role> for RoleWrapper<'r> {
 
 impl<'r> Serialize for RoleWrapper<'r> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let mut role = s.serialize_struct("CachedRole", 4)?;
+        let len = 8
+            + self.0.tags.is_some() as usize
+            + self.0.icon.is_some() as usize
+            + self.0.unicode_emoji.is_some() as usize;
+
+        let mut role = s.serialize_struct("CachedRole", len)?;
 
         role.serialize_field("a", &self.0.id)?;
         role.serialize_field("b", &self.0.name)?;
         role.serialize_field("c", &self.0.permissions)?;
         role.serialize_field("d", &self.0.position)?;
+        role.serialize_field("e", &self.0.color)?;
+        role.serialize_field("f", &self.0.hoist)?;
+        role.serialize_field("g", &self.0.mentionable)?;
+        role.serialize_field("h", &self.0.managed)?;
+
+        if let Some(ref tags) = self.0.tags {
+            role.serialize_field("i", &RoleTagsWrapper(tags))?;
+        }
+
+        if let Some(ref icon) = self.0.icon {
+            role.serialize_field("j", icon)?;
+        }
+
+        if let Some(ref unicode_emoji) = self.0.unicode_emoji {
+            role.serialize_field("k", unicode_emoji)?;
+        }
 
         role.end()
     }
 }
 
+pub struct RoleTagsWrapper<'r>(pub &'r RoleTags);
+
+impl<'r> Serialize for RoleTagsWrapper<'r> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 1 + self.0.bot_id.is_some() as usize + self.0.integration_id.is_some() as usize;
+
+        let mut tags = s.serialize_struct("CachedRoleTags", len)?;
+
+        if let Some(ref bot_id) = self.0.bot_id {
+            tags.serialize_field("a", bot_id)?;
+        }
+
+        if let Some(ref integration_id) = self.0.integration_id {
+            tags.serialize_field("b", integration_id)?;
+        }
+
+        tags.serialize_field("c", &self.0.premium_subscriber)?;
+
+        tags.end()
+    }
+}
+
+pub struct UserWrapper<'u>(pub &'u User);
+
+impl<'u> From<&'u User> for UserWrapper<'u> {
+    fn from(user: &'u User) -> Self {
+        Self(user)
+    }
+}
+
+impl<'u> Serialize for UserWrapper<'u> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 4 + self.0.avatar.is_some() as usize;
+        let mut user = s.serialize_struct("CachedUser", len)?;
+
+        if let Some(ref avatar) = self.0.avatar {
+            user.serialize_field("a", avatar)?;
+        }
+
+        user.serialize_field("b", &self.0.bot)?;
+        user.serialize_field("c", &self.0.discriminator)?;
+        user.serialize_field("d", &self.0.id)?;
+        user.serialize_field("e", &self.0.name)?;
+
+        user.end()
+    }
+}
+
+/// Member data that is indexed beyond the member itself
+pub trait MemberData {
+    fn user(&self) -> &User;
+    fn roles(&self) -> &[RoleId];
+}
+
 pub struct MemberWrapper<'m>(pub &'m Member);
 
 impl<'m> From<&'m Member> for MemberWrapper<'m> {
@@ -116,7 +251,13 @@ impl<'m> From<&'m Member> for MemberWrapper<'m> {
 
 impl<'m> Serialize for MemberWrapper<'m> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let len = 2 + self.0.nick.is_some() as usize + !self.0.roles.is_empty() as usize;
+        let len = 4
+            + self.0.nick.is_some() as usize
+            + !self.0.roles.is_empty() as usize
+            + self.0.avatar.is_some() as usize
+            + self.0.premium_since.is_some() as usize
+            + self.0.communication_disabled_until.is_some() as usize;
+
         let mut member = s.serialize_struct("CachedMember", len)?;
 
         member.serialize_field("a", &self.0.guild_id)?;
@@ -130,11 +271,36 @@ impl<'m> Serialize for MemberWrapper<'m> {
         }
 
         member.serialize_field("d", &self.0.user.id)?;
+        member.serialize_field("e", &self.0.joined_at)?;
+
+        if let Some(ref avatar) = self.0.avatar {
+            member.serialize_field("f", avatar)?;
+        }
+
+        member.serialize_field("g", &self.0.pending)?;
+
+        if let Some(ref premium_since) = self.0.premium_since {
+            member.serialize_field("h", premium_since)?;
+        }
+
+        if let Some(ref until) = self.0.communication_disabled_until {
+            member.serialize_field("i", until)?;
+        }
 
         member.end()
     }
 }
 
+impl<'m> MemberData for MemberWrapper<'m> {
+    fn user(&self) -> &User {
+        &self.0.user
+    }
+
+    fn roles(&self) -> &[RoleId] {
+        &self.0.roles
+    }
+}
+
 pub struct PartialMemberWrapper<'m> {
     guild: GuildId,
     member: &'m PartialMember,
@@ -153,7 +319,13 @@ impl<'m> From<(&'m PartialMember, GuildId, &'m User)> for PartialMemberWrapper<'
 
 impl<'m> Serialize for PartialMemberWrapper<'m> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let len = 2 + self.member.nick.is_some() as usize + !self.member.roles.is_empty() as usize;
+        let len = 4
+            + self.member.nick.is_some() as usize
+            + !self.member.roles.is_empty() as usize
+            + self.member.avatar.is_some() as usize
+            + self.member.premium_since.is_some() as usize
+            + self.member.communication_disabled_until.is_some() as usize;
+
         let mut member = s.serialize_struct("CachedMember", len)?;
 
         member.serialize_field("a", &self.guild)?;
@@ -167,11 +339,37 @@ impl<'m> Serialize for PartialMemberWrapper<'m> {
         }
 
         member.serialize_field("d", &self.user.id)?;
+        member.serialize_field("e", &self.member.joined_at)?;
+
+        if let Some(ref avatar) = self.member.avatar {
+            member.serialize_field("f", avatar)?;
+        }
+
+        // Partial members don't specify whether they passed membership screening
+        member.serialize_field("g", &false)?;
+
+        if let Some(ref premium_since) = self.member.premium_since {
+            member.serialize_field("h", premium_since)?;
+        }
+
+        if let Some(ref until) = self.member.communication_disabled_until {
+            member.serialize_field("i", until)?;
+        }
 
         member.end()
     }
 }
 
+impl<'m> MemberData for PartialMemberWrapper<'m> {
+    fn user(&self) -> &User {
+        self.user
+    }
+
+    fn roles(&self) -> &[RoleId] {
+        &self.member.roles
+    }
+}
+
 pub struct MemberUpdateWrapper<'m>(&'m MemberUpdate);
 
 impl<'m> From<&'m MemberUpdate> for MemberUpdateWrapper<'m> {
@@ -182,7 +380,13 @@ impl<'m> From<&'m MemberUpdate> for MemberUpdateWrapper<'m> {
 
 impl<'m> Serialize for MemberUpdateWrapper<'m> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let len = 2 + self.0.nick.is_some() as usize + !self.0.roles.is_empty() as usize;
+        let len = 4
+            + self.0.nick.is_some() as usize
+            + !self.0.roles.is_empty() as usize
+            + self.0.avatar.is_some() as usize
+            + self.0.premium_since.is_some() as usize
+            + self.0.communication_disabled_until.is_some() as usize;
+
         let mut member = s.serialize_struct("CachedMember", len)?;
 
         member.serialize_field("a", &self.0.guild_id)?;
@@ -196,11 +400,36 @@ impl<'m> Serialize for MemberUpdateWrapper<'m> {
         }
 
         member.serialize_field("d", &self.0.user.id)?;
+        member.serialize_field("e", &self.0.joined_at)?;
+
+        if let Some(ref avatar) = self.0.avatar {
+            member.serialize_field("f", avatar)?;
+        }
+
+        member.serialize_field("g", &self.0.pending)?;
+
+        if let Some(ref premium_since) = self.0.premium_since {
+            member.serialize_field("h", premium_since)?;
+        }
+
+        if let Some(ref until) = self.0.communication_disabled_until {
+            member.serialize_field("i", until)?;
+        }
 
         member.end()
     }
 }
 
+impl<'m> MemberData for MemberUpdateWrapper<'m> {
+    fn user(&self) -> &User {
+        &self.0.user
+    }
+
+    fn roles(&self) -> &[RoleId] {
+        &self.0.roles
+    }
+}
+
 pub struct TextChannelWrapper<'c>(pub &'c TextChannel);
 
 impl<'c> From<&'c TextChannel> for TextChannelWrapper<'c> {
@@ -211,9 +440,12 @@ impl<'c> From<&'c TextChannel> for TextChannelWrapper<'c> {
 
 impl<'c> Serialize for TextChannelWrapper<'c> {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let len = 2
+        let len = 5
             + self.0.guild_id.is_some() as usize
-            + !self.0.permission_overwrites.is_empty() as usize;
+            + !self.0.permission_overwrites.is_empty() as usize
+            + self.0.parent_id.is_some() as usize
+            + self.0.topic.is_some() as usize
+            + self.0.rate_limit_per_user.is_some() as usize;
 
         let mut channel = s.serialize_struct("CachedTextChannel", len)?;
 
@@ -228,6 +460,23 @@ impl<'c> Serialize for TextChannelWrapper<'c> {
             channel.serialize_field("d", &self.0.permission_overwrites)?;
         }
 
+        if let Some(ref parent_id) = self.0.parent_id {
+            channel.serialize_field("e", parent_id)?;
+        }
+
+        if let Some(ref topic) = self.0.topic {
+            channel.serialize_field("f", topic)?;
+        }
+
+        channel.serialize_field("g", &self.0.nsfw)?;
+        channel.serialize_field("h", &self.0.position)?;
+
+        if let Some(ref rate_limit) = self.0.rate_limit_per_user {
+            channel.serialize_field("i", rate_limit)?;
+        }
+
+        channel.serialize_field("j", &self.0.kind)?;
+
         channel.end()
     }
 }
@@ -288,35 +537,530 @@ impl<'c> Serialize for PrivateThreadWrapper<'c> {
     }
 }
 
+pub struct NewsThreadWrapper<'c>(pub &'c NewsThread);
+
+impl<'c> From<&'c NewsThread> for NewsThreadWrapper<'c> {
+    fn from(channel: &'c NewsThread) -> Self {
+        Self(channel)
+    }
+}
+
+impl<'c> Serialize for NewsThreadWrapper<'c> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 2 + self.0.guild_id.is_some() as usize + self.0.parent_id.is_some() as usize;
+        let mut channel = s.serialize_struct("CachedThread", len)?;
+
+        if let Some(ref guild) = self.0.guild_id {
+            channel.serialize_field("a", guild)?;
+        }
+
+        channel.serialize_field("b", &self.0.id)?;
+        channel.serialize_field("c", &self.0.name)?;
+
+        if let Some(ref parent_id) = self.0.parent_id {
+            channel.serialize_field("d", parent_id)?;
+        }
+
+        channel.end()
+    }
+}
+
+pub struct CategoryChannelWrapper<'c>(pub &'c CategoryChannel);
+
+impl<'c> From<&'c CategoryChannel> for CategoryChannelWrapper<'c> {
+    fn from(channel: &'c CategoryChannel) -> Self {
+        Self(channel)
+    }
+}
+
+impl<'c> Serialize for CategoryChannelWrapper<'c> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 2
+            + self.0.guild_id.is_some() as usize
+            + !self.0.permission_overwrites.is_empty() as usize;
+
+        let mut channel = s.serialize_struct("CachedCategoryChannel", len)?;
+
+        if let Some(ref guild) = self.0.guild_id {
+            channel.serialize_field("a", guild)?;
+        }
+
+        channel.serialize_field("b", &self.0.id)?;
+        channel.serialize_field("c", &self.0.name)?;
+
+        if !self.0.permission_overwrites.is_empty() {
+            channel.serialize_field("d", &self.0.permission_overwrites)?;
+        }
+
+        channel.end()
+    }
+}
+
+pub struct VoiceChannelWrapper<'c>(pub &'c VoiceChannel);
+
+impl<'c> From<&'c VoiceChannel> for VoiceChannelWrapper<'c> {
+    fn from(channel: &'c VoiceChannel) -> Self {
+        Self(channel)
+    }
+}
+
+impl<'c> Serialize for VoiceChannelWrapper<'c> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 2
+            + self.0.guild_id.is_some() as usize
+            + !self.0.permission_overwrites.is_empty() as usize
+            + self.0.parent_id.is_some() as usize;
+
+        let mut channel = s.serialize_struct("CachedVoiceChannel", len)?;
+
+        if let Some(ref guild) = self.0.guild_id {
+            channel.serialize_field("a", guild)?;
+        }
+
+        channel.serialize_field("b", &self.0.id)?;
+        channel.serialize_field("c", &self.0.name)?;
+
+        if !self.0.permission_overwrites.is_empty() {
+            channel.serialize_field("d", &self.0.permission_overwrites)?;
+        }
+
+        if let Some(ref parent_id) = self.0.parent_id {
+            channel.serialize_field("e", parent_id)?;
+        }
+
+        channel.end()
+    }
+}
+
+/// Voice states of a guild's create event don't contain the guild id
+/// so it's passed separately, only states within a channel are cached
+pub struct VoiceStateWrapper<'v> {
+    channel: ChannelId,
+    guild: GuildId,
+    state: &'v VoiceState,
+}
+
+impl<'v> VoiceStateWrapper<'v> {
+    pub fn new(state: &'v VoiceState, guild: GuildId) -> Option<Self> {
+        let wrapper = Self {
+            channel: state.channel_id?,
+            guild,
+            state,
+        };
+
+        Some(wrapper)
+    }
+
+    pub const fn channel_id(&self) -> ChannelId {
+        self.channel
+    }
+
+    pub const fn user_id(&self) -> UserId {
+        self.state.user_id
+    }
+}
+
+impl<'v> Serialize for VoiceStateWrapper<'v> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 10 + self.state.request_to_speak_timestamp.is_some() as usize;
+        let mut state = s.serialize_struct("CachedVoiceState", len)?;
+
+        state.serialize_field("a", &self.channel)?;
+        state.serialize_field("b", &self.state.deaf)?;
+        state.serialize_field("c", &self.guild)?;
+        state.serialize_field("d", &self.state.mute)?;
+        state.serialize_field("e", &self.state.self_deaf)?;
+        state.serialize_field("f", &self.state.self_mute)?;
+        state.serialize_field("g", &self.state.self_stream)?;
+        state.serialize_field("h", &self.state.self_video)?;
+        state.serialize_field("i", &self.state.suppress)?;
+        state.serialize_field("j", &self.state.user_id)?;
+
+        if let Some(ref timestamp) = self.state.request_to_speak_timestamp {
+            state.serialize_field("k", timestamp)?;
+        }
+
+        state.end()
+    }
+}
+
+/// Both presences of a guild's create event and presence updates
+pub struct PresenceWrapper<'p> {
+    activities: &'p [Activity],
+    guild: GuildId,
+    status: Status,
+    user: UserId,
+}
+
+impl<'p> PresenceWrapper<'p> {
+    pub const fn guild_id(&self) -> GuildId {
+        self.guild
+    }
+
+    pub const fn user_id(&self) -> UserId {
+        self.user
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self.status, Status::Offline | Status::Invisible)
+    }
+}
+
+impl<'p> From<&'p Presence> for PresenceWrapper<'p> {
+    fn from(presence: &'p Presence) -> Self {
+        Self {
+            activities: &presence.activities,
+            guild: presence.guild_id,
+            status: presence.status,
+            user: presence.user.id(),
+        }
+    }
+}
+
+impl<'p> From<&'p PresenceUpdate> for PresenceWrapper<'p> {
+    fn from(presence: &'p PresenceUpdate) -> Self {
+        Self {
+            activities: &presence.activities,
+            guild: presence.guild_id,
+            status: presence.status,
+            user: presence.user.id(),
+        }
+    }
+}
+
+impl<'p> Serialize for PresenceWrapper<'p> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 3 + !self.activities.is_empty() as usize;
+        let mut presence = s.serialize_struct("CachedPresence", len)?;
+
+        if !self.activities.is_empty() {
+            let activities: Vec<_> = self.activities.iter().map(ActivityWrapper).collect();
+            presence.serialize_field("a", &activities)?;
+        }
+
+        presence.serialize_field("b", &self.guild)?;
+        presence.serialize_field("c", &self.status)?;
+        presence.serialize_field("d", &self.user)?;
+
+        presence.end()
+    }
+}
+
+pub struct ActivityWrapper<'a>(pub &'a Activity);
+
+impl<'a> Serialize for ActivityWrapper<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let assets = self.0.assets.as_ref();
+        let large_text = assets.and_then(|assets| assets.large_text.as_ref());
+        let small_text = assets.and_then(|assets| assets.small_text.as_ref());
+        let start = self.0.timestamps.as_ref().and_then(|ts| ts.start);
+
+        let len = 2
+            + self.0.application_id.is_some() as usize
+            + self.0.details.is_some() as usize
+            + self.0.state.is_some() as usize
+            + self.0.url.is_some() as usize
+            + large_text.is_some() as usize
+            + small_text.is_some() as usize
+            + start.is_some() as usize;
+
+        let mut activity = s.serialize_struct("CachedActivity", len)?;
+
+        if let Some(ref application_id) = self.0.application_id {
+            activity.serialize_field("a", application_id)?;
+        }
+
+        if let Some(ref details) = self.0.details {
+            activity.serialize_field("b", details)?;
+        }
+
+        activity.serialize_field("c", &self.0.kind)?;
+        activity.serialize_field("d", &self.0.name)?;
+
+        if let Some(ref state) = self.0.state {
+            activity.serialize_field("e", state)?;
+        }
+
+        if let Some(ref url) = self.0.url {
+            activity.serialize_field("f", url)?;
+        }
+
+        if let Some(large_text) = large_text {
+            activity.serialize_field("g", large_text)?;
+        }
+
+        if let Some(small_text) = small_text {
+            activity.serialize_field("h", small_text)?;
+        }
+
+        if let Some(ref start) = start {
+            activity.serialize_field("i", start)?;
+        }
+
+        activity.end()
+    }
+}
+
+pub struct MessageWrapper<'m>(pub &'m Message);
+
+impl<'m> From<&'m Message> for MessageWrapper<'m> {
+    fn from(message: &'m Message) -> Self {
+        Self(message)
+    }
+}
+
+impl<'m> Serialize for MessageWrapper<'m> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let reference = self
+            .0
+            .reference
+            .as_ref()
+            .and_then(|reference| reference.message_id);
+
+        let len = 5
+            + !self.0.attachments.is_empty() as usize
+            + self.0.edited_timestamp.is_some() as usize
+            + self.0.guild_id.is_some() as usize
+            + reference.is_some() as usize;
+
+        let mut message = s.serialize_struct("CachedMessage", len)?;
+
+        if !self.0.attachments.is_empty() {
+            let urls: Vec<_> = self.0.attachments.iter().map(|a| &a.url).collect();
+            message.serialize_field("a", &urls)?;
+        }
+
+        message.serialize_field("b", &self.0.author.id)?;
+        message.serialize_field("c", &self.0.channel_id)?;
+        message.serialize_field("d", &self.0.content)?;
+
+        if let Some(ref edited_timestamp) = self.0.edited_timestamp {
+            message.serialize_field("e", edited_timestamp)?;
+        }
+
+        if let Some(ref guild) = self.0.guild_id {
+            message.serialize_field("f", guild)?;
+        }
+
+        message.serialize_field("g", &self.0.id)?;
+
+        if let Some(ref reference) = reference {
+            message.serialize_field("h", reference)?;
+        }
+
+        message.serialize_field("i", &self.0.timestamp)?;
+
+        message.end()
+    }
+}
+
+/// Emojis don't contain their guild id so it's passed separately
+pub struct EmojiWrapper<'e> {
+    emoji: &'e Emoji,
+    guild: GuildId,
+}
+
+impl<'e> From<(&'e Emoji, GuildId)> for EmojiWrapper<'e> {
+    fn from((emoji, guild): (&'e Emoji, GuildId)) -> Self {
+        Self { emoji, guild }
+    }
+}
+
+impl<'e> Serialize for EmojiWrapper<'e> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 7 + !self.emoji.roles.is_empty() as usize;
+        let mut emoji = s.serialize_struct("CachedEmoji", len)?;
+
+        emoji.serialize_field("a", &self.emoji.animated)?;
+        emoji.serialize_field("b", &self.emoji.available)?;
+        emoji.serialize_field("c", &self.guild)?;
+        emoji.serialize_field("d", &self.emoji.id)?;
+        emoji.serialize_field("e", &self.emoji.managed)?;
+        emoji.serialize_field("f", &self.emoji.name)?;
+        emoji.serialize_field("g", &self.emoji.require_colons)?;
+
+        if !self.emoji.roles.is_empty() {
+            emoji.serialize_field("h", &self.emoji.roles)?;
+        }
+
+        emoji.end()
+    }
+}
+
+pub struct StickerWrapper<'s>(pub &'s Sticker);
+
+impl<'s> From<&'s Sticker> for StickerWrapper<'s> {
+    fn from(sticker: &'s Sticker) -> Self {
+        Self(sticker)
+    }
+}
+
+impl<'s> Serialize for StickerWrapper<'s> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let len = 5 + self.0.description.is_some() as usize + self.0.guild_id.is_some() as usize;
+        let mut sticker = s.serialize_struct("CachedSticker", len)?;
+
+        sticker.serialize_field("a", &self.0.available)?;
+
+        if let Some(ref description) = self.0.description {
+            sticker.serialize_field("b", description)?;
+        }
+
+        sticker.serialize_field("c", &self.0.format_type)?;
+
+        if let Some(ref guild) = self.0.guild_id {
+            sticker.serialize_field("d", guild)?;
+        }
+
+        sticker.serialize_field("e", &self.0.id)?;
+        sticker.serialize_field("f", &self.0.name)?;
+        sticker.serialize_field("g", &self.0.tags)?;
+
+        sticker.end()
+    }
+}
+
+pub struct StageInstanceWrapper<'s>(pub &'s StageInstance);
+
+impl<'s> From<&'s StageInstance> for StageInstanceWrapper<'s> {
+    fn from(stage: &'s StageInstance) -> Self {
+        Self(stage)
+    }
+}
+
+impl<'s> Serialize for StageInstanceWrapper<'s> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut stage = s.serialize_struct("CachedStageInstance", 6)?;
+
+        stage.serialize_field("a", &self.0.channel_id)?;
+        stage.serialize_field("b", &self.0.discoverable_disabled)?;
+        stage.serialize_field("c", &self.0.guild_id)?;
+        stage.serialize_field("d", &self.0.id)?;
+        stage.serialize_field("e", &self.0.privacy_level)?;
+        stage.serialize_field("f", &self.0.topic)?;
+
+        stage.end()
+    }
+}
+
+pub struct ScheduledEventWrapper<'e>(pub &'e ScheduledEvent);
+
+impl<'e> From<&'e ScheduledEvent> for ScheduledEventWrapper<'e> {
+    fn from(event: &'e ScheduledEvent) -> Self {
+        Self(event)
+    }
+}
+
+impl<'e> Serialize for ScheduledEventWrapper<'e> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let location = self
+            .0
+            .entity_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.location.as_ref());
+
+        let len = 6
+            + self.0.channel_id.is_some() as usize
+            + self.0.creator_id.is_some() as usize
+            + self.0.description.is_some() as usize
+            + self.0.image.is_some() as usize
+            + location.is_some() as usize
+            + self.0.scheduled_end_time.is_some() as usize
+            + self.0.user_count.is_some() as usize;
+
+        let mut event = s.serialize_struct("CachedScheduledEvent", len)?;
+
+        if let Some(ref channel_id) = self.0.channel_id {
+            event.serialize_field("a", channel_id)?;
+        }
+
+        if let Some(ref creator_id) = self.0.creator_id {
+            event.serialize_field("b", creator_id)?;
+        }
+
+        if let Some(ref description) = self.0.description {
+            event.serialize_field("c", description)?;
+        }
+
+        event.serialize_field("d", &self.0.entity_type)?;
+        event.serialize_field("e", &self.0.guild_id)?;
+        event.serialize_field("f", &self.0.id)?;
+
+        if let Some(ref image) = self.0.image {
+            event.serialize_field("g", image)?;
+        }
+
+        if let Some(location) = location {
+            event.serialize_field("h", location)?;
+        }
+
+        event.serialize_field("i", &self.0.name)?;
+
+        if let Some(ref scheduled_end_time) = self.0.scheduled_end_time {
+            event.serialize_field("j", scheduled_end_time)?;
+        }
+
+        event.serialize_field("k", &self.0.scheduled_start_time)?;
+        event.serialize_field("l", &self.0.status)?;
+
+        if let Some(ref user_count) = self.0.user_count {
+            event.serialize_field("m", user_count)?;
+        }
+
+        event.end()
+    }
+}
+
 pub enum BasicGuildChannel<'c> {
+    Category(&'c CategoryChannel),
+    News(&'c TextChannel),
+    NewsThread(&'c NewsThread),
     PrivateThread(&'c PrivateThread),
     PublicThread(&'c PublicThread),
+    Stage(&'c VoiceChannel),
     Text(&'c TextChannel),
+    Voice(&'c VoiceChannel),
 }
 
 impl<'c> BasicGuildChannel<'c> {
     pub const fn guild_id(&self) -> Option<GuildId> {
         match self {
+            Self::Category(c) => c.guild_id,
+            Self::News(c) => c.guild_id,
+            Self::NewsThread(c) => c.guild_id,
             Self::PrivateThread(c) => c.guild_id,
             Self::PublicThread(c) => c.guild_id,
+            Self::Stage(c) => c.guild_id,
             Self::Text(c) => c.guild_id,
+            Self::Voice(c) => c.guild_id,
         }
     }
 
     pub const fn id(&self) -> ChannelId {
         match self {
+            Self::Category(c) => c.id,
+            Self::News(c) => c.id,
+            Self::NewsThread(c) => c.id,
             Self::PrivateThread(c) => c.id,
             Self::PublicThread(c) => c.id,
+            Self::Stage(c) => c.id,
             Self::Text(c) => c.id,
+            Self::Voice(c) => c.id,
         }
     }
+}
 
-    pub fn from(channel: &'c GuildChannel) -> Option<Self> {
+impl<'c> From<&'c GuildChannel> for BasicGuildChannel<'c> {
+    fn from(channel: &'c GuildChannel) -> Self {
         match channel {
-            GuildChannel::PrivateThread(c) => Some(Self::PrivateThread(c)),
-            GuildChannel::PublicThread(c) => Some(Self::PublicThread(c)),
-            GuildChannel::Text(c) => Some(Self::Text(c)),
-            _ => None,
+            GuildChannel::Category(c) => Self::Category(c),
+            GuildChannel::NewsThread(c) => Self::NewsThread(c),
+            GuildChannel::PrivateThread(c) => Self::PrivateThread(c),
+            GuildChannel::PublicThread(c) => Self::PublicThread(c),
+            GuildChannel::Stage(c) => Self::Stage(c),
+            GuildChannel::Text(c) if c.kind == ChannelType::GuildNews => Self::News(c),
+            GuildChannel::Text(c) => Self::Text(c),
+            GuildChannel::Voice(c) => Self::Voice(c),
         }
     }
 }
@@ -333,6 +1077,24 @@ impl<'c> Serialize for BasicGuildChannel<'c> {
             BasicGuildChannel::Text(c) => {
                 s.serialize_newtype_variant("CachedGuildChannel", 2, "c", &TextChannelWrapper(c))
             }
+            BasicGuildChannel::Category(c) => s.serialize_newtype_variant(
+                "CachedGuildChannel",
+                3,
+                "d",
+                &CategoryChannelWrapper(c),
+            ),
+            BasicGuildChannel::News(c) => {
+                s.serialize_newtype_variant("CachedGuildChannel", 4, "e", &TextChannelWrapper(c))
+            }
+            BasicGuildChannel::NewsThread(c) => {
+                s.serialize_newtype_variant("CachedGuildChannel", 5, "f", &NewsThreadWrapper(c))
+            }
+            BasicGuildChannel::Stage(c) => {
+                s.serialize_newtype_variant("CachedGuildChannel", 6, "g", &VoiceChannelWrapper(c))
+            }
+            BasicGuildChannel::Voice(c) => {
+                s.serialize_newtype_variant("CachedGuildChannel", 7, "h", &VoiceChannelWrapper(c))
+            }
         }
     }
 }
@@ -1,130 +1,316 @@
-use std::{borrow::Cow, iter};
+use std::{
+    borrow::Cow,
+    iter,
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use deadpool_redis::redis::AsyncCommands;
 use hashbrown::HashMap;
 use serde::Serialize;
-use serde_cbor::Error as CborError;
 use twilight_model::{
     application::interaction::Interaction,
-    channel::Channel,
-    gateway::event::Event,
-    guild::{Member, Role},
-    id::GuildId,
+    channel::{
+        message::sticker::Sticker, stage_instance::StageInstance, thread::ThreadMember, Channel,
+        GuildChannel,
+    },
+    gateway::{
+        event::Event,
+        payload::incoming::{GuildStickersUpdate, MessageUpdate},
+    },
+    guild::{Emoji, Member, Role},
+    id::{ChannelId, GuildId, MessageId, RoleId, UserId},
+    voice::VoiceState,
 };
 
 use crate::{
-    constants::{CHANNEL_KEYS, GUILD_KEYS, MEMBER_KEYS, ROLE_KEYS},
+    backend::Batch,
+    constants::{
+        CHANNEL_KEYS, EXPIRING_KEYS, GUILD_CHANNELS, GUILD_EMOJIS, GUILD_KEYS, GUILD_MEMBERS,
+        GUILD_ROLES, GUILD_SCHEDULED_EVENTS, GUILD_STAGE_INSTANCES, GUILD_STICKERS, MEMBER_COUNT,
+        MEMBER_KEYS, MESSAGES, ROLE_KEYS, ROLE_MEMBERS, THREAD_MEMBERS, THREAD_MEMBER_COUNT,
+        USER_GUILDS, USER_KEYS, VOICE_MEMBERS,
+    },
     model::{
-        BasicGuildChannel, GuildWrapper, MemberUpdateWrapper, MemberWrapper, PartialGuildWrapper,
-        PartialMemberWrapper, RedisKey, RoleWrapper, SessionInfo,
+        BasicGuildChannel, CachedMessage, EmojiWrapper, GuildWrapper, MemberData,
+        MemberUpdateWrapper, MemberWrapper, MessageWrapper, PartialGuildWrapper,
+        PartialMemberWrapper, PresenceWrapper, RedisKey, RoleWrapper, ScheduledEvent,
+        ScheduledEventId, ScheduledEventWrapper, SessionInfo, StageInstanceWrapper, StickerWrapper,
+        UserWrapper, VoiceStateWrapper,
     },
     CacheResult,
 };
 
 use super::Cache;
 
+/// Minimum amount of seconds between prunes that are triggered by reads
+const PRUNE_INTERVAL: u64 = 60;
+
 impl Cache {
     #[inline]
     pub async fn cache_channel(&self, channel: &Channel) -> CacheResult<()> {
-        if let Channel::Guild(channel) = channel {
-            if let Some(c) = BasicGuildChannel::from(channel) {
-                self.set(RedisKey::from(&c), c).await?;
-            }
-        }
+        let mut batch = self.batch();
+        self.stage_channel(&mut batch, channel)?;
 
-        Ok(())
+        self.backend.apply(&batch).await
     }
 
     #[inline]
     pub async fn cache_member(&self, member: &Member) -> CacheResult<()> {
-        let wrapper = MemberWrapper::from(member);
+        let mut batch = self.batch();
+        self.stage_member(&mut batch, member)?;
 
-        if let Some(ttl) = self.config.member_ttl {
-            self.set_with_expire(RedisKey::from(member), wrapper, ttl)
-                .await
-        } else {
-            self.set(member.into(), wrapper).await
-        }
+        self.backend.apply(&batch).await
     }
 
     #[inline]
     pub async fn cache_role(&self, role: &Role, guild: GuildId) -> CacheResult<()> {
-        self.set(RedisKey::from((guild, role.id)), RoleWrapper::from(role))
-            .await
+        let mut batch = self.batch();
+        self.stage_role(&mut batch, role, guild)?;
+
+        self.backend.apply(&batch).await
     }
 
     #[inline]
     pub async fn cache_shards(&self, shards: u64) -> CacheResult<()> {
-        self.set(RedisKey::Shards, shards).await
+        let mut batch = self.batch();
+        self.stage_all(&mut batch, iter::once((RedisKey::Shards, shards)))?;
+
+        self.backend.apply(&batch).await
     }
 
     #[inline]
     pub async fn cache_sessions(&self, sessions: &HashMap<u64, SessionInfo>) -> CacheResult<()> {
-        self.set_with_expire(RedisKey::Sessions, sessions, 300)
+        let mut batch = self.batch();
+        self.stage_with_expire(&mut batch, RedisKey::Sessions, sessions, 300)?;
+
+        self.backend.apply(&batch).await
+    }
+
+    /// Replace the cached stickers of the guild with those of a
+    /// `GUILD_STICKERS_UPDATE` dispatch which is not part of [`Event`].
+    pub async fn cache_guild_stickers(&self, update: &GuildStickersUpdate) -> CacheResult<()> {
+        let mut batch = self.batch();
+        self.stage_clear_index(&mut batch, update.guild_id, GUILD_STICKERS)
+            .await?;
+        self.stage_stickers(&mut batch, update.guild_id, &update.stickers)?;
+
+        self.backend.apply(&batch).await
+    }
+
+    /// Cache a created or updated [`ScheduledEvent`]
+    pub async fn cache_scheduled_event(&self, event: &ScheduledEvent) -> CacheResult<()> {
+        let mut batch = self.batch();
+        self.stage_scheduled_events(&mut batch, iter::once(event))?;
+
+        self.backend.apply(&batch).await
+    }
+
+    /// Replace the cached scheduled events of the guild
+    /// e.g. with the `guild_scheduled_events` of a raw GuildCreate payload.
+    ///
+    /// Scheduled events are kept when the guild is refreshed through [`Cache::update`]
+    /// and only removed once the guild is deleted.
+    pub async fn cache_scheduled_events(
+        &self,
+        guild: GuildId,
+        events: &[ScheduledEvent],
+    ) -> CacheResult<()> {
+        let mut batch = self.batch();
+        self.stage_clear_index(&mut batch, guild, GUILD_SCHEDULED_EVENTS)
+            .await?;
+        self.stage_scheduled_events(&mut batch, events)?;
+
+        self.backend.apply(&batch).await
+    }
+
+    #[inline]
+    pub async fn delete_scheduled_event(
+        &self,
+        guild: GuildId,
+        event: ScheduledEventId,
+    ) -> CacheResult<()> {
+        let mut batch = self.batch();
+        self.stage_del_all(&mut batch, iter::once(RedisKey::from((guild, event))));
+
+        self.backend.apply(&batch).await
+    }
+
+    /// Remove members, users and presences whose cached data expired from all index sets.
+    ///
+    /// Only relevant if [`CacheConfig::member_ttl`](crate::model::CacheConfig::member_ttl)
+    /// or [`CacheConfig::presence_ttl`](crate::model::CacheConfig::presence_ttl) is set.
+    ///
+    /// The cache does not schedule this on its own, callers must run it periodically
+    /// e.g. every few minutes so that the index sets don't keep growing.
+    /// Reading member indexes also prunes but at most once per minute and only
+    /// if there are reads. Expired entries are skipped by reads either way.
+    pub async fn prune_expired_members(&self) -> CacheResult<()> {
+        let now = unix_timestamp();
+        self.last_prune.store(now, Ordering::Relaxed);
+
+        let expired = self
+            .get_sorted_members::<RedisKey>(EXPIRING_KEYS, now)
+            .await?;
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = self.batch();
+
+        // Keys that were cached again since reading them have a new score and are skipped
+        for key in expired {
+            let mut index = HashMap::new();
+            populate_members(&key, &mut index);
+
+            let sets = index
+                .into_iter()
+                .flat_map(|(set, entries)| {
+                    entries
+                        .into_iter()
+                        .map(move |entry| (set.clone().into_owned(), entry))
+                })
+                .collect();
+
+            batch.sorted_set_remove_expired(EXPIRING_KEYS.to_owned(), key.to_string(), now, sets);
+        }
+
+        self.backend.apply(&batch).await
+    }
+
+    /// Prune expired members and presences before reading member indexes
+    /// unless that already happened within the last `PRUNE_INTERVAL` seconds
+    pub(crate) async fn prune_if_due(&self) -> CacheResult<()> {
+        if self.config.member_ttl.is_none() && self.config.presence_ttl.is_none() {
+            return Ok(());
+        }
+
+        let now = unix_timestamp();
+        let last = self.last_prune.load(Ordering::Relaxed);
+
+        // Only one of multiple concurrent readers does the pruning
+        if now < last + PRUNE_INTERVAL
+            || self
+                .last_prune
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return Ok(());
+        }
+
+        self.prune_expired_members().await
+    }
+
+    /// Retrieve the members, users and presences that expired
+    /// but are not yet pruned from the index sets
+    pub(crate) async fn expired_keys(&self) -> CacheResult<Vec<RedisKey>> {
+        if self.config.member_ttl.is_none() && self.config.presence_ttl.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.get_sorted_members(EXPIRING_KEYS, unix_timestamp())
             .await
     }
 
+    /// Update the cache with the event's content.
+    ///
+    /// All writes of an event are applied together in a single atomic batch.
     pub async fn update(&self, event: &Event) -> CacheResult<()> {
+        let mut batch = self.batch();
+
         match event {
-            Event::ChannelCreate(e) => self.cache_channel(e).await?,
+            Event::ChannelCreate(e) => self.stage_channel(&mut batch, e)?,
             Event::ChannelDelete(e) => {
                 if let Channel::Guild(channel) = &e.0 {
-                    if let Some(c) = BasicGuildChannel::from(channel) {
-                        self.del(RedisKey::from(&c)).await?;
-                    }
+                    let c = BasicGuildChannel::from(channel);
+                    self.stage_del_all(&mut batch, iter::once(RedisKey::from(&c)));
+                    batch.del(format!("{}:{}", MESSAGES, c.id()));
                 }
             }
-            Event::ChannelUpdate(e) => self.cache_channel(e).await?,
+            Event::ChannelUpdate(e) => self.stage_channel(&mut batch, e)?,
             Event::GuildCreate(e) => {
-                self.clear_guild(e.id).await?;
+                self.stage_clear_guild(&mut batch, e.id).await?;
 
                 // Cache channels
-                if !e.channels.is_empty() {
-                    let channels = e
-                        .channels
-                        .iter()
-                        .filter_map(BasicGuildChannel::from)
-                        .map(|channel| (RedisKey::from(&channel), channel));
-
-                    self.set_all(channels).await?;
-                }
+                let channels = e
+                    .channels
+                    .iter()
+                    .map(BasicGuildChannel::from)
+                    .map(|channel| (RedisKey::from(&channel), channel));
+
+                self.stage_all(&mut batch, channels)?;
 
                 // Cache roles
-                if !e.roles.is_empty() {
-                    let roles = e
-                        .roles
-                        .iter()
-                        .map(|role| (RedisKey::from((e.id, role.id)), RoleWrapper::from(role)));
+                let roles = e
+                    .roles
+                    .iter()
+                    .map(|role| (RedisKey::from((e.id, role.id)), RoleWrapper::from(role)));
 
-                    self.set_all(roles).await?;
-                }
+                self.stage_all(&mut batch, roles)?;
 
                 // Cache members
-                if !e.members.is_empty() {
-                    let members = e
-                        .members
-                        .iter()
-                        .map(MemberWrapper::from)
-                        .map(|member| (RedisKey::from(&member), member));
-
-                    if let Some(ttl) = self.config.member_ttl {
-                        let keys = members
-                            .map(|(key, member)| Ok((key, serde_cbor::to_vec(&member)?)))
-                            .collect::<Result<Vec<_>, CborError>>()?;
-
-                        self.set_all_with_expire(&keys, ttl).await?;
-                    } else {
-                        self.set_all(members).await?;
+                let members = e
+                    .members
+                    .iter()
+                    .map(MemberWrapper::from)
+                    .map(|member| (RedisKey::from(&member), member));
+
+                self.stage_members(&mut batch, members)?;
+
+                // Cache voice states
+                self.stage_voice_states(&mut batch, e.id, &e.voice_states)?;
+
+                // Cache presences
+                let presences = e.presences.iter().map(PresenceWrapper::from);
+                self.stage_presences(&mut batch, presences)?;
+
+                // Cache emojis and stickers
+                self.stage_emojis(&mut batch, e.id, &e.emojis)?;
+                self.stage_stickers(&mut batch, e.id, &e.stickers)?;
+
+                // Cache stage instances
+                let stages = e.stage_instances.iter().map(stage_instance_entry);
+                self.stage_all(&mut batch, stages)?;
+
+                // Cache the guild itself
+                let guild = (RedisKey::from(e.id), GuildWrapper::from(&e.0));
+                self.stage_all(&mut batch, iter::once(guild))?;
+
+                if let Some(count) = e.member_count {
+                    stage_member_count(&mut batch, e.id, count);
+                }
+            }
+            Event::GuildDelete(e) => {
+                // Messages are kept during outages, the guild's channels
+                // and thus their messages come back with its GuildCreate
+                if !e.unavailable {
+                    let key = format!("{}:{}", GUILD_CHANNELS, e.id);
+
+                    for channel in self.get_members::<RedisKey>(key).await? {
+                        if let RedisKey::Channel { channel, .. } = channel {
+                            batch.del(format!("{}:{}", MESSAGES, channel));
+                        }
                     }
+
+                    // Scheduled events are kept too since the GuildCreate doesn't restore them
+                    self.stage_clear_index(&mut batch, e.id, GUILD_SCHEDULED_EVENTS)
+                        .await?;
                 }
 
-                // Cache the guild itself
-                self.set(e.id.into(), GuildWrapper::from(&e.0)).await?;
+                self.stage_clear_guild(&mut batch, e.id).await?;
+            }
+            Event::GuildEmojisUpdate(e) => {
+                self.stage_clear_index(&mut batch, e.guild_id, GUILD_EMOJIS)
+                    .await?;
+                self.stage_emojis(&mut batch, e.guild_id, &e.emojis)?;
             }
-            Event::GuildDelete(e) => self.clear_guild(e.id).await?,
             Event::GuildUpdate(e) => {
-                self.set(e.id.into(), PartialGuildWrapper::from(&e.0))
-                    .await?
+                let guild = PartialGuildWrapper::from(&e.0);
+                self.stage_all(&mut batch, iter::once((RedisKey::from(e.id), guild)))?;
+
+                if let Some(count) = e.member_count {
+                    stage_member_count(&mut batch, e.id, count);
+                }
             }
             Event::InteractionCreate(e) => {
                 let (guild, member) = match &e.0 {
@@ -137,64 +323,81 @@ impl Cache {
                     if let Some(user) = &member.user {
                         let key = RedisKey::from((guild, user.id));
                         let member = PartialMemberWrapper::from((member, guild, user));
-
-                        if let Some(ttl) = self.config.member_ttl {
-                            self.set_with_expire(key, member, ttl).await?;
-                        } else {
-                            self.set(key, member).await?;
-                        }
+                        self.stage_members(&mut batch, iter::once((key, member)))?;
                     }
                 }
             }
-            Event::MemberAdd(e) => self.cache_member(e).await?,
-            Event::MemberRemove(e) => self.del(RedisKey::from((e.guild_id, e.user.id))).await?,
-            Event::MemberUpdate(e) => {
+            Event::MemberAdd(e) => {
+                self.stage_member(&mut batch, e)?;
+                batch.incr(format!("{}:{}", MEMBER_COUNT, e.guild_id), 1);
+            }
+            Event::MemberRemove(e) => {
+                if let Some(member) = self.member(e.guild_id, e.user.id).await? {
+                    stage_role_members_remove(&mut batch, e.user.id, &member.roles);
+                }
+
                 let key = RedisKey::from((e.guild_id, e.user.id));
-                let member = MemberUpdateWrapper::from(e.as_ref());
+                self.stage_del_all(&mut batch, iter::once(key));
+                batch.incr(format!("{}:{}", MEMBER_COUNT, e.guild_id), -1);
+            }
+            Event::MemberUpdate(e) => {
+                if let Some(member) = self.member(e.guild_id, e.user.id).await? {
+                    let removed: Vec<_> = member
+                        .roles
+                        .into_iter()
+                        .filter(|role| !e.roles.contains(role))
+                        .collect();
 
-                if let Some(ttl) = self.config.member_ttl {
-                    self.set_with_expire(key, member, ttl).await?;
-                } else {
-                    self.set(key, member).await?;
+                    stage_role_members_remove(&mut batch, e.user.id, &removed);
                 }
+
+                let key = RedisKey::from((e.guild_id, e.user.id));
+                let member = MemberUpdateWrapper::from(e.as_ref());
+                self.stage_members(&mut batch, iter::once((key, member)))?;
             }
             Event::MemberChunk(e) => {
-                let keys = e
+                let members = e
                     .members
                     .iter()
                     .map(MemberWrapper::from)
                     .map(|member| (RedisKey::from(&member), member));
 
-                if let Some(ttl) = self.config.member_ttl {
-                    let keys = keys
-                        .map(|(key, member)| Ok((key, serde_cbor::to_vec(&member)?)))
-                        .collect::<Result<Vec<_>, CborError>>()?;
-
-                    self.set_all_with_expire(&keys, ttl).await?;
-                } else {
-                    self.set_all(keys).await?;
-                }
+                self.stage_members(&mut batch, members)?;
             }
             Event::MessageCreate(e) => {
                 if let (Some(member), Some(guild)) = (&e.member, e.guild_id) {
                     let key = RedisKey::from((guild, e.author.id));
                     let member = PartialMemberWrapper::from((member, guild, &e.author));
+                    self.stage_members(&mut batch, iter::once((key, member)))?;
+                }
 
-                    if let Some(ttl) = self.config.member_ttl {
-                        self.set_with_expire(key, member, ttl).await?;
-                    } else {
-                        self.set(key, member).await?;
-                    }
+                if let Some(max_len) = self.config.message_cache_size {
+                    let key = format!("{}:{}", MESSAGES, e.channel_id);
+                    let message = serde_cbor::to_vec(&MessageWrapper::from(&e.0))?;
+                    batch.list_push(key, vec![message], max_len);
                 }
             }
+            Event::MessageDelete(e) => {
+                self.stage_message_delete(&mut batch, e.channel_id, &[e.id])
+                    .await?
+            }
+            Event::MessageDeleteBulk(e) => {
+                self.stage_message_delete(&mut batch, e.channel_id, &e.ids)
+                    .await?
+            }
+            Event::MessageUpdate(e) => self.stage_message_update(&mut batch, e).await?,
+            Event::PresenceUpdate(e) => {
+                let presence = PresenceWrapper::from(e.as_ref());
+                self.stage_presences(&mut batch, iter::once(presence))?;
+            }
             Event::ReactionAdd(e) => {
                 if let Some(member) = &e.member {
-                    self.cache_member(member).await?;
+                    self.stage_member(&mut batch, member)?;
                 }
             }
             Event::ReactionRemove(e) => {
                 if let Some(member) = &e.member {
-                    self.cache_member(member).await?;
+                    self.stage_member(&mut batch, member)?;
                 }
             }
             Event::Ready(_e) => {
@@ -202,81 +405,114 @@ impl Cache {
                 // self.set(RedisKey::BotUser, CurrentUserWrapper::from(&e.user))
                 //     .await?;
             }
-            Event::RoleCreate(e) => self.cache_role(&e.role, e.guild_id).await?,
-            Event::RoleDelete(e) => self.del(RedisKey::from((e.guild_id, e.role_id))).await?,
-            Event::RoleUpdate(e) => self.cache_role(&e.role, e.guild_id).await?,
-            Event::ThreadCreate(e) => self.cache_channel(e).await?,
-            Event::ThreadDelete(e) => {
+            Event::RoleCreate(e) => self.stage_role(&mut batch, &e.role, e.guild_id)?,
+            Event::RoleDelete(e) => {
+                let key = RedisKey::from((e.guild_id, e.role_id));
+                self.stage_del_all(&mut batch, iter::once(key));
+            }
+            Event::RoleUpdate(e) => self.stage_role(&mut batch, &e.role, e.guild_id)?,
+            Event::StageInstanceCreate(e) => {
+                self.stage_all(&mut batch, iter::once(stage_instance_entry(&e.0)))?;
+            }
+            Event::StageInstanceDelete(e) => {
+                let key = RedisKey::from((e.guild_id, e.id));
+                self.stage_del_all(&mut batch, iter::once(key));
+            }
+            Event::StageInstanceUpdate(e) => {
+                self.stage_all(&mut batch, iter::once(stage_instance_entry(&e.0)))?;
+            }
+            Event::ThreadCreate(e) => {
+                self.stage_channel(&mut batch, e)?;
+
                 if let Channel::Guild(channel) = &e.0 {
-                    if let Some(c) = BasicGuildChannel::from(channel) {
-                        self.del(RedisKey::from(&c)).await?;
-                    }
+                    stage_thread_creator(&mut batch, channel);
                 }
             }
+            Event::ThreadDelete(e) => {
+                let key = RedisKey::Channel {
+                    guild: Some(e.guild_id),
+                    channel: e.id,
+                };
+
+                self.stage_del_all(&mut batch, iter::once(key));
+                batch.del(format!("{}:{}", MESSAGES, e.id));
+            }
             Event::ThreadListSync(e) => {
                 // Cache members
-                if !e.members.is_empty() {
-                    let keys = e
-                        .members
-                        .iter()
-                        .filter_map(|member| member.member.as_ref())
-                        .map(MemberWrapper::from)
-                        .map(|member| (RedisKey::from(&member), member));
-
-                    if let Some(ttl) = self.config.member_ttl {
-                        let keys = keys
-                            .map(|(key, member)| Ok((key, serde_cbor::to_vec(&member)?)))
-                            .collect::<Result<Vec<_>, CborError>>()?;
-
-                        self.set_all_with_expire(&keys, ttl).await?;
-                    } else {
-                        self.set_all(keys).await?;
-                    }
-                }
+                let members = e
+                    .members
+                    .iter()
+                    .filter_map(|member| member.member.as_ref())
+                    .map(MemberWrapper::from)
+                    .map(|member| (RedisKey::from(&member), member));
+
+                self.stage_members(&mut batch, members)?;
+
+                // Cache thread memberships
+                stage_thread_members(&mut batch, None, &e.members);
 
                 // Cache channels
-                if !e.threads.is_empty() {
-                    let keys = e
-                        .threads
-                        .iter()
-                        .filter_map(|c| {
-                            if let Channel::Guild(channel) = c {
-                                BasicGuildChannel::from(channel)
-                            } else {
-                                None
-                            }
-                        })
-                        .map(|channel| (RedisKey::from(&channel), channel));
-
-                    self.set_all(keys).await?;
+                let channels = e
+                    .threads
+                    .iter()
+                    .filter_map(|c| {
+                        if let Channel::Guild(channel) = c {
+                            Some(BasicGuildChannel::from(channel))
+                        } else {
+                            None
+                        }
+                    })
+                    .map(|channel| (RedisKey::from(&channel), channel));
+
+                self.stage_all(&mut batch, channels)?;
+
+                for thread in e.threads.iter() {
+                    if let Channel::Guild(thread) = thread {
+                        stage_thread_member_count(&mut batch, thread);
+                    }
                 }
             }
             Event::ThreadMemberUpdate(e) => {
                 if let Some(member) = &e.member {
-                    self.cache_member(member).await?;
+                    self.stage_member(&mut batch, member)?;
                 }
+
+                stage_thread_members(&mut batch, None, iter::once(&e.0));
             }
             Event::ThreadMembersUpdate(e) => {
-                if !e.added_members.is_empty() {
-                    let keys = e
-                        .added_members
-                        .iter()
-                        .filter_map(|member| member.member.as_ref())
-                        .map(MemberWrapper::from)
-                        .map(|member| (RedisKey::from(&member), member));
-
-                    if let Some(ttl) = self.config.member_ttl {
-                        let keys = keys
-                            .map(|(key, member)| Ok((key, serde_cbor::to_vec(&member)?)))
-                            .collect::<Result<Vec<_>, CborError>>()?;
-
-                        self.set_all_with_expire(&keys, ttl).await?;
-                    } else {
-                        self.set_all(keys).await?;
+                let members = e
+                    .added_members
+                    .iter()
+                    .filter_map(|member| member.member.as_ref())
+                    .map(MemberWrapper::from)
+                    .map(|member| (RedisKey::from(&member), member));
+
+                self.stage_members(&mut batch, members)?;
+
+                stage_thread_members(&mut batch, Some(e.id), &e.added_members);
+
+                let removed = e
+                    .removed_member_ids
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+
+                batch.set_remove(format!("{}:{}", THREAD_MEMBERS, e.id), removed);
+
+                let key = format!("{}:{}", THREAD_MEMBER_COUNT, e.id);
+                batch.set(key, e.member_count.to_string().into_bytes(), None);
+            }
+            Event::ThreadUpdate(e) => self.stage_channel(&mut batch, e)?,
+            Event::VoiceStateUpdate(e) => {
+                if let Some(guild) = e.0.guild_id {
+                    if let Some(member) = &e.0.member {
+                        self.stage_member(&mut batch, member)?;
                     }
+
+                    self.stage_voice_state_update(&mut batch, guild, &e.0)
+                        .await?;
                 }
             }
-            Event::ThreadUpdate(e) => self.cache_channel(e).await?,
             Event::UserUpdate(_e) => {
                 // * Handled in bot context instead
                 // self.set(RedisKey::BotUser, CurrentUserWrapper::from(&e.0))
@@ -285,136 +521,458 @@ impl Cache {
             _ => {}
         }
 
+        self.backend.apply(&batch).await
+    }
+
+    pub(crate) fn batch(&self) -> Batch {
+        Batch::with_prefix(self.config.key_prefix.clone())
+    }
+
+    fn stage_channel(&self, batch: &mut Batch, channel: &Channel) -> CacheResult<()> {
+        if let Channel::Guild(channel) = channel {
+            let c = BasicGuildChannel::from(channel);
+            self.stage_all(batch, iter::once((RedisKey::from(&c), c)))?;
+
+            stage_thread_member_count(batch, channel);
+        }
+
         Ok(())
     }
 
-    async fn set<T>(&self, key: RedisKey, value: T) -> CacheResult<()>
+    fn stage_member(&self, batch: &mut Batch, member: &Member) -> CacheResult<()> {
+        let member = (RedisKey::from(member), MemberWrapper::from(member));
+
+        self.stage_members(batch, iter::once(member))
+    }
+
+    fn stage_role(&self, batch: &mut Batch, role: &Role, guild: GuildId) -> CacheResult<()> {
+        let role = (RedisKey::from((guild, role.id)), RoleWrapper::from(role));
+
+        self.stage_all(batch, iter::once(role))
+    }
+
+    /// Stage members, their users and role memberships, respecting the configured member TTL
+    fn stage_members<I, T>(&self, batch: &mut Batch, members: I) -> CacheResult<()>
     where
-        T: Serialize,
+        I: IntoIterator<Item = (RedisKey, T)>,
+        T: Serialize + MemberData,
     {
-        self.set_all(iter::once((key, value))).await?;
+        let members: Vec<_> = members.into_iter().collect();
+        let mut role_members = HashMap::new();
 
-        Ok(())
+        for (_, member) in &members {
+            let user = member.user().id.to_string();
+
+            for role in member.roles() {
+                role_members
+                    .entry(*role)
+                    .or_insert_with(Vec::new)
+                    .push(user.clone());
+            }
+        }
+
+        for (role, users) in role_members {
+            batch.set_add(format!("{}:{}", ROLE_MEMBERS, role), users);
+        }
+
+        let users = members.iter().map(|(_, member)| {
+            let user = member.user();
+
+            (RedisKey::from(user), UserWrapper::from(user))
+        });
+
+        self.stage_expiring(batch, users, self.config.member_ttl)?;
+
+        self.stage_expiring(batch, members, self.config.member_ttl)
     }
 
-    async fn set_all<I, T>(&self, keys: I) -> CacheResult<()>
+    /// Stage presences if enabled, respecting the configured presence TTL.
+    ///
+    /// Offline users have their presence removed.
+    fn stage_presences<'p, I>(&self, batch: &mut Batch, presences: I) -> CacheResult<()>
+    where
+        I: IntoIterator<Item = PresenceWrapper<'p>>,
+    {
+        if !self.config.cache_presences {
+            return Ok(());
+        }
+
+        let (offline, online): (Vec<_>, Vec<_>) = presences
+            .into_iter()
+            .map(|presence| (RedisKey::from(&presence), presence))
+            .partition(|(_, presence)| presence.is_offline());
+
+        self.stage_del_all(batch, offline.into_iter().map(|(key, _)| key));
+
+        self.stage_expiring(batch, online, self.config.presence_ttl)
+    }
+
+    /// Stage values that expire after the given amount of seconds
+    fn stage_expiring<I, T>(
+        &self,
+        batch: &mut Batch,
+        values: I,
+        seconds: Option<usize>,
+    ) -> CacheResult<()>
     where
         I: IntoIterator<Item = (RedisKey, T)>,
         T: Serialize,
     {
-        let mut members = HashMap::new();
+        let seconds = match seconds {
+            Some(seconds) => seconds,
+            None => return self.stage_all(batch, values),
+        };
 
-        let keys = keys
-            .into_iter()
-            .inspect(|(key, _)| populate_members(key, &mut members))
-            .map(|(key, value)| serde_cbor::to_vec(&value).map(|value| (key, value)))
-            .collect::<Result<Vec<(RedisKey, Vec<u8>)>, CborError>>()?;
+        let expires_at = unix_timestamp() + seconds as u64;
+        let mut index = HashMap::new();
+        let mut expiring = Vec::new();
 
-        if keys.is_empty() {
-            return Ok(());
-        }
+        for (key, value) in values {
+            let ttl = self.ttl(&key, seconds);
+
+            if ttl.is_some() {
+                expiring.push((expires_at, key.to_string()));
+            }
 
-        let mut conn = self.redis.get().await?;
-        conn.set_multiple(&keys).await?;
+            populate_members(&key, &mut index);
+            batch.set(key.to_string(), serde_cbor::to_vec(&value)?, ttl);
+        }
 
-        for (key, value) in members {
-            conn.sadd(key.as_ref(), value).await?;
+        for (key, value) in index {
+            batch.set_add(key.into_owned(), value);
         }
 
+        // Keep track of when keys expire so they can be removed from the index sets
+        batch.sorted_set_add(EXPIRING_KEYS.to_owned(), expiring);
+
         Ok(())
     }
 
-    async fn set_with_expire<T>(&self, key: RedisKey, value: T, seconds: usize) -> CacheResult<()>
+    fn stage_voice_states<'v, I>(
+        &self,
+        batch: &mut Batch,
+        guild: GuildId,
+        states: I,
+    ) -> CacheResult<()>
     where
-        T: Serialize,
+        I: IntoIterator<Item = &'v VoiceState>,
     {
-        let bytes = serde_cbor::to_vec(&value)?;
-        let mut conn = self.redis.get().await?;
+        let mut channels = HashMap::new();
 
-        // Don't expire the cached member data of the bot itself
-        if key.user_id().filter(|id| id == &self.bot_id).is_none() {
-            conn.set_ex(key, bytes, seconds).await?;
-        } else {
-            conn.set(key, bytes).await?;
+        let states = states
+            .into_iter()
+            .filter_map(|state| VoiceStateWrapper::new(state, guild))
+            .map(|state| {
+                let user = state.user_id();
+
+                channels
+                    .entry(state.channel_id())
+                    .or_insert_with(Vec::new)
+                    .push(user.to_string());
+
+                (RedisKey::VoiceState { guild, user }, state)
+            });
+
+        self.stage_all(batch, states)?;
+
+        for (channel, users) in channels {
+            batch.set_add(format!("{}:{}", VOICE_MEMBERS, channel), users);
         }
 
         Ok(())
     }
 
-    async fn set_all_with_expire(
+    /// Move the user between the voice channel indexes or remove the voice state
+    /// if the user left voice entirely
+    async fn stage_voice_state_update(
         &self,
-        keys: &[(RedisKey, Vec<u8>)],
-        seconds: usize,
+        batch: &mut Batch,
+        guild: GuildId,
+        state: &VoiceState,
     ) -> CacheResult<()> {
-        if keys.is_empty() {
-            return Ok(());
-        }
+        let user = state.user_id;
 
-        let mut conn = self.redis.get().await?;
-        conn.set_multiple(keys).await?;
-
-        for (key, _) in keys {
-            // Don't expire the cached member data of the bot itself
-            if key.user_id().filter(|id| id == &self.bot_id).is_none() {
-                conn.expire(key, seconds).await?;
+        if let Some(prev) = self.voice_state(guild, user).await? {
+            if Some(prev.channel_id) != state.channel_id {
+                let key = format!("{}:{}", VOICE_MEMBERS, prev.channel_id);
+                batch.set_remove(key, vec![user.to_string()]);
             }
         }
 
-        Ok(())
+        if state.channel_id.is_some() {
+            self.stage_voice_states(batch, guild, iter::once(state))
+        } else {
+            let key = RedisKey::VoiceState { guild, user };
+            self.stage_del_all(batch, iter::once(key));
+
+            Ok(())
+        }
     }
 
-    async fn del(&self, key: RedisKey) -> CacheResult<()> {
+    fn stage_all<I, T>(&self, batch: &mut Batch, keys: I) -> CacheResult<()>
+    where
+        I: IntoIterator<Item = (RedisKey, T)>,
+        T: Serialize,
+    {
         let mut members = HashMap::new();
-        populate_members(&key, &mut members);
 
-        let mut conn = self.redis.get().await?;
-        conn.del(key).await?;
+        for (key, value) in keys {
+            populate_members(&key, &mut members);
+            batch.set(key.to_string(), serde_cbor::to_vec(&value)?, None);
+        }
 
         for (key, value) in members {
-            conn.srem(key.as_ref(), value).await?;
+            batch.set_add(key.into_owned(), value);
         }
 
         Ok(())
     }
 
-    async fn del_all<I>(&self, keys: I) -> CacheResult<()>
+    fn stage_with_expire<T>(
+        &self,
+        batch: &mut Batch,
+        key: RedisKey,
+        value: T,
+        seconds: usize,
+    ) -> CacheResult<()>
+    where
+        T: Serialize,
+    {
+        let ttl = self.ttl(&key, seconds);
+        batch.set(key.to_string(), serde_cbor::to_vec(&value)?, ttl);
+
+        Ok(())
+    }
+
+    fn ttl(&self, key: &RedisKey, seconds: usize) -> Option<usize> {
+        // Don't expire the cached member and user data of the bot itself
+        Some(seconds).filter(|_| key.user_id().filter(|id| id == &self.bot_id).is_none())
+    }
+
+    fn stage_del_all<I>(&self, batch: &mut Batch, keys: I)
+    where
+        I: IntoIterator<Item = RedisKey>,
+    {
+        let keys: Vec<_> = keys.into_iter().collect();
+
+        for key in &keys {
+            batch.del(key.to_string());
+
+            // Threads, voice channels and roles keep track of their members.
+            // Recent messages are only removed once the channel itself is deleted
+            // so that they survive the guild being refreshed by a GuildCreate.
+            match key {
+                RedisKey::Channel { channel, .. } => {
+                    batch.del(format!("{}:{}", THREAD_MEMBERS, channel));
+                    batch.del(format!("{}:{}", THREAD_MEMBER_COUNT, channel));
+                    batch.del(format!("{}:{}", VOICE_MEMBERS, channel));
+                }
+                RedisKey::Role { role, .. } => batch.del(format!("{}:{}", ROLE_MEMBERS, role)),
+                RedisKey::Guild { guild } => batch.del(format!("{}:{}", MEMBER_COUNT, guild)),
+                _ => {}
+            }
+        }
+
+        // Users are shared across guilds so they're only removed
+        // once they're no longer a member of any cached guild
+        let users: Vec<_> = keys
+            .iter()
+            .filter_map(|key| match key {
+                RedisKey::Member { user, .. } => Some(*user),
+                _ => None,
+            })
+            .collect();
+
+        self.stage_unindex(batch, keys);
+
+        for user in users {
+            let refs = format!("{}:{}", USER_GUILDS, user);
+            let key = RedisKey::from(user).to_string();
+            batch.del_unreferenced(key, refs, USER_KEYS.to_owned());
+        }
+    }
+
+    /// Remove the keys from all index sets without deleting their values
+    fn stage_unindex<I>(&self, batch: &mut Batch, keys: I)
     where
         I: IntoIterator<Item = RedisKey>,
     {
         let mut members = HashMap::new();
+        let mut expiring = Vec::new();
 
-        let keys = keys
-            .into_iter()
-            .inspect(|key| populate_members(key, &mut members))
-            .collect::<Vec<RedisKey>>();
+        for key in keys {
+            populate_members(&key, &mut members);
+
+            if let RedisKey::Member { .. } | RedisKey::User { .. } | RedisKey::Presence { .. } = key
+            {
+                expiring.push(key.to_string());
+            }
+        }
 
-        if keys.is_empty() {
+        for (key, value) in members {
+            batch.set_remove(key.into_owned(), value);
+        }
+
+        batch.sorted_set_remove(EXPIRING_KEYS.to_owned(), expiring);
+    }
+
+    async fn stage_message_update(
+        &self,
+        batch: &mut Batch,
+        update: &MessageUpdate,
+    ) -> CacheResult<()> {
+        if self.config.message_cache_size.is_none() {
             return Ok(());
         }
 
-        let mut conn = self.redis.get().await?;
-        conn.del(keys).await?;
+        let key = format!("{}:{}", MESSAGES, update.channel_id);
+        let values = self
+            .backend
+            .list_front(&self.prefixed(&key), usize::MAX)
+            .await?;
 
-        for (key, value) in members {
-            conn.srem(key.as_ref(), value).await?;
+        for value in values {
+            let mut message: CachedMessage = serde_cbor::from_slice(&value)?;
+
+            if message.id != update.id {
+                continue;
+            }
+
+            if let Some(ref content) = update.content {
+                message.content = content.to_owned();
+            }
+
+            if let Some(ref attachments) = update.attachments {
+                message.attachments = attachments.iter().map(|a| a.url.to_owned()).collect();
+            }
+
+            if update.edited_timestamp.is_some() {
+                message.edited_timestamp = update.edited_timestamp;
+            }
+
+            // The list may have changed since it was read so instead of writing to an index,
+            // the new value is placed in front of the old one which is then removed.
+            // If the old value is gone by now, neither of the two has any effect.
+            batch.list_insert(key.clone(), value.clone(), serde_cbor::to_vec(&message)?);
+            batch.list_remove(key, value);
+
+            break;
         }
 
         Ok(())
     }
 
-    async fn clear_guild(&self, guild: GuildId) -> CacheResult<()> {
-        let members = self
-            .get_members::<RedisKey>(format!("{}:{}", GUILD_KEYS, guild))
+    async fn stage_message_delete(
+        &self,
+        batch: &mut Batch,
+        channel: ChannelId,
+        ids: &[MessageId],
+    ) -> CacheResult<()> {
+        if self.config.message_cache_size.is_none() {
+            return Ok(());
+        }
+
+        let key = format!("{}:{}", MESSAGES, channel);
+        let values = self
+            .backend
+            .list_front(&self.prefixed(&key), usize::MAX)
             .await?;
 
-        self.del_all(members).await?;
-        self.del(RedisKey::Guild { guild }).await?;
+        // List elements are removed by value so the stored bytes are used as is
+        for value in values {
+            let message: CachedMessage = serde_cbor::from_slice(&value)?;
+
+            if ids.contains(&message.id) {
+                batch.list_remove(key.clone(), value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stage_emojis(&self, batch: &mut Batch, guild: GuildId, emojis: &[Emoji]) -> CacheResult<()> {
+        let emojis = emojis.iter().map(|emoji| {
+            (
+                RedisKey::from((guild, emoji.id)),
+                EmojiWrapper::from((emoji, guild)),
+            )
+        });
+
+        self.stage_all(batch, emojis)
+    }
+
+    fn stage_scheduled_events<'e, I>(&self, batch: &mut Batch, events: I) -> CacheResult<()>
+    where
+        I: IntoIterator<Item = &'e ScheduledEvent>,
+    {
+        let events = events.into_iter().map(|event| {
+            (
+                RedisKey::from((event.guild_id, event.id)),
+                ScheduledEventWrapper::from(event),
+            )
+        });
+
+        self.stage_all(batch, events)
+    }
+
+    fn stage_stickers(
+        &self,
+        batch: &mut Batch,
+        guild: GuildId,
+        stickers: &[Sticker],
+    ) -> CacheResult<()> {
+        let stickers = stickers.iter().map(|sticker| {
+            (
+                RedisKey::from((guild, sticker.id)),
+                StickerWrapper::from(sticker),
+            )
+        });
+
+        self.stage_all(batch, stickers)
+    }
+
+    /// Remove all entries of one of the guild's index sets, used when
+    /// an update event contains the full list of entries
+    async fn stage_clear_index(
+        &self,
+        batch: &mut Batch,
+        guild: GuildId,
+        index: &str,
+    ) -> CacheResult<()> {
+        let key = format!("{}:{}", index, guild);
+        let members = self.get_members::<RedisKey>(key).await?;
+        let keys = members.into_iter().map(|key| key.with_guild(guild));
+
+        self.stage_del_all(batch, keys);
+
+        Ok(())
+    }
+
+    async fn stage_clear_guild(&self, batch: &mut Batch, guild: GuildId) -> CacheResult<()> {
+        let guild_keys = format!("{}:{}", GUILD_KEYS, guild);
+        let members = self.get_members::<RedisKey>(guild_keys.clone()).await?;
+        let keys = members.into_iter().map(|key| key.with_guild(guild));
+
+        self.stage_del_all(batch, keys);
+        self.stage_del_all(batch, iter::once(RedisKey::Guild { guild }));
+        batch.del(guild_keys);
+
+        for index in [
+            GUILD_CHANNELS,
+            GUILD_ROLES,
+            GUILD_MEMBERS,
+            GUILD_EMOJIS,
+            GUILD_STICKERS,
+            GUILD_STAGE_INSTANCES,
+        ] {
+            batch.del(format!("{}:{}", index, guild));
+        }
 
         Ok(())
     }
 }
 
-type RedisMembers = HashMap<Cow<'static, str>, Vec<RedisKey>>;
+type RedisMembers = HashMap<Cow<'static, str>, Vec<String>>;
 
 fn populate_members(key: &RedisKey, members: &mut RedisMembers) {
     match key {
@@ -423,20 +981,62 @@ fn populate_members(key: &RedisKey, members: &mut RedisMembers) {
 
             if let Some(guild) = guild {
                 populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+                populate_member(format!("{}:{}", GUILD_CHANNELS, guild), *key, members);
             }
         }
         RedisKey::Guild { .. } => populate_member(GUILD_KEYS, *key, members),
-        RedisKey::Member { guild, .. } => {
+        RedisKey::Member { guild, user } => {
             populate_member(MEMBER_KEYS, *key, members);
             populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+            populate_member(format!("{}:{}", GUILD_MEMBERS, guild), *key, members);
+
+            let guild = RedisKey::Guild { guild: *guild };
+            populate_member(format!("{}:{}", USER_GUILDS, user), guild, members);
         }
         RedisKey::Role { guild, .. } => {
             populate_member(ROLE_KEYS, *key, members);
 
             if let Some(guild) = guild {
                 populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+                populate_member(format!("{}:{}", GUILD_ROLES, guild), *key, members);
             }
         }
+        RedisKey::User { .. } => populate_member(USER_KEYS, *key, members),
+        RedisKey::Emoji {
+            guild: Some(guild), ..
+        } => {
+            populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+            populate_member(format!("{}:{}", GUILD_EMOJIS, guild), *key, members);
+        }
+        RedisKey::Sticker {
+            guild: Some(guild), ..
+        } => {
+            populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+            populate_member(format!("{}:{}", GUILD_STICKERS, guild), *key, members);
+        }
+        RedisKey::StageInstance {
+            guild: Some(guild), ..
+        } => {
+            populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+            populate_member(
+                format!("{}:{}", GUILD_STAGE_INSTANCES, guild),
+                *key,
+                members,
+            );
+        }
+        // Not part of the guild keys so that they're kept when a GuildCreate refreshes the guild
+        RedisKey::ScheduledEvent {
+            guild: Some(guild), ..
+        } => {
+            populate_member(
+                format!("{}:{}", GUILD_SCHEDULED_EVENTS, guild),
+                *key,
+                members,
+            );
+        }
+        RedisKey::VoiceState { guild, .. } | RedisKey::Presence { guild, .. } => {
+            populate_member(format!("{}:{}", GUILD_KEYS, guild), *key, members);
+        }
         _ => {}
     }
 }
@@ -445,5 +1045,421 @@ fn populate_member(key: impl Into<Cow<'static, str>>, value: RedisKey, members:
     members
         .entry(key.into())
         .or_insert_with(Vec::new)
-        .push(value)
+        .push(value.to_string())
+}
+
+fn stage_instance_entry(stage: &StageInstance) -> (RedisKey, StageInstanceWrapper<'_>) {
+    let key = RedisKey::from((stage.guild_id, stage.id));
+
+    (key, StageInstanceWrapper::from(stage))
+}
+
+fn stage_thread_members<'m>(
+    batch: &mut Batch,
+    thread: Option<ChannelId>,
+    members: impl IntoIterator<Item = &'m ThreadMember>,
+) {
+    let mut threads = HashMap::new();
+
+    for member in members {
+        let user = member
+            .user_id
+            .or_else(|| member.member.as_ref().map(|member| member.user.id));
+
+        if let (Some(thread), Some(user)) = (member.id.or(thread), user) {
+            threads
+                .entry(thread)
+                .or_insert_with(Vec::new)
+                .push(user.to_string());
+        }
+    }
+
+    for (thread, users) in threads {
+        batch.set_add(format!("{}:{}", THREAD_MEMBERS, thread), users);
+    }
+}
+
+/// The member count is stored as plain integer so that joins
+/// and leaves can adjust it through `INCRBY` without reading it first
+fn stage_member_count(batch: &mut Batch, guild: GuildId, count: u64) {
+    let key = format!("{}:{}", MEMBER_COUNT, guild);
+    batch.set(key, count.to_string().into_bytes(), None);
+}
+
+fn stage_role_members_remove(batch: &mut Batch, user: UserId, roles: &[RoleId]) {
+    for role in roles {
+        batch.set_remove(format!("{}:{}", ROLE_MEMBERS, role), vec![user.to_string()]);
+    }
+}
+
+/// Threads only tell how many members they have, not who they are.
+/// Comparing the count with the cached members shows whether all of them are known.
+fn stage_thread_member_count(batch: &mut Batch, channel: &GuildChannel) {
+    let (id, count) = match channel {
+        GuildChannel::NewsThread(c) => (c.id, c.member_count),
+        GuildChannel::PrivateThread(c) => (c.id, c.member_count),
+        GuildChannel::PublicThread(c) => (c.id, c.member_count),
+        _ => return,
+    };
+
+    let key = format!("{}:{}", THREAD_MEMBER_COUNT, id);
+    batch.set(key, count.to_string().into_bytes(), None);
+}
+
+/// The creator of a thread as well as the current user are members of a new thread
+fn stage_thread_creator(batch: &mut Batch, channel: &GuildChannel) {
+    let (id, owner, member) = match channel {
+        GuildChannel::NewsThread(c) => (c.id, c.owner_id, &c.member),
+        GuildChannel::PrivateThread(c) => (c.id, c.owner_id, &c.member),
+        GuildChannel::PublicThread(c) => (c.id, c.owner_id, &c.member),
+        _ => return,
+    };
+
+    let users = owner
+        .into_iter()
+        .chain(member.as_ref().and_then(|member| member.user_id))
+        .map(|user| user.to_string())
+        .collect();
+
+    batch.set_add(format!("{}:{}", THREAD_MEMBERS, id), users);
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use twilight_model::{
+        channel::{ChannelType, GuildChannel, TextChannel},
+        datetime::Timestamp,
+        gateway::{
+            event::Event,
+            payload::incoming::{GuildCreate, GuildDelete, MemberRemove, PresenceUpdate},
+            presence::{ClientStatus, Status, UserOrId},
+        },
+        guild::{Emoji, Permissions},
+        id::{ChannelId, EmojiId, GuildId},
+    };
+
+    use crate::{
+        constants::{
+            EXPIRING_KEYS, GUILD_CHANNELS, GUILD_EMOJIS, GUILD_KEYS, GUILD_MEMBERS, GUILD_ROLES,
+            GUILD_SCHEDULED_EVENTS, GUILD_STAGE_INSTANCES, GUILD_STICKERS, MEMBER_COUNT,
+            ROLE_MEMBERS, USER_GUILDS,
+        },
+        model::{
+            CacheConfig, RedisKey, ScheduledEvent, ScheduledEventEntityType, ScheduledEventId,
+            ScheduledEventStatus,
+        },
+        test_util::{self, guild_id, role_id, user_id},
+        Cache,
+    };
+
+    use super::unix_timestamp;
+
+    const GUILD: GuildId = guild_id(10);
+
+    async fn remove_member(cache: &Cache, user: u64) {
+        let member = test_util::member(GUILD, user_id(user), Vec::new());
+
+        let event = Event::MemberRemove(MemberRemove {
+            guild_id: GUILD,
+            user: member.user,
+        });
+
+        cache.update(&event).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn member_count_follows_joins_and_leaves() {
+        let cache = test_util::cache();
+
+        let mut guild = test_util::guild(GUILD, user_id(2));
+        guild.member_count = Some(5);
+        let event = Event::GuildCreate(Box::new(GuildCreate(guild)));
+        cache.update(&event).await.unwrap();
+
+        test_util::add_member(&cache, GUILD, user_id(3), Vec::new()).await;
+        test_util::add_member(&cache, GUILD, user_id(4), Vec::new()).await;
+        remove_member(&cache, 3).await;
+
+        let guild = cache.guild(GUILD).await.unwrap().unwrap();
+        assert_eq!(guild.member_count, Some(6));
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_members_that_were_cached_again() {
+        let config = CacheConfig {
+            member_ttl: Some(60),
+            ..Default::default()
+        };
+
+        let cache = test_util::cache_with(config);
+        test_util::add_member(&cache, GUILD, user_id(3), Vec::new()).await;
+
+        // A prune that read the member as expired right before it was cached again
+        let key = RedisKey::from((GUILD, user_id(3))).to_string();
+        let index = format!("{}:{}", GUILD_MEMBERS, GUILD);
+
+        let mut batch = cache.batch();
+        let sets = vec![(index, key.clone())];
+        batch.sorted_set_remove_expired(EXPIRING_KEYS.to_owned(), key, unix_timestamp(), sets);
+        cache.backend.apply(&batch).await.unwrap();
+
+        assert_eq!(cache.guild_members(GUILD).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_members_are_skipped_before_pruning() {
+        let config = CacheConfig {
+            member_ttl: Some(1),
+            ..Default::default()
+        };
+
+        let cache = test_util::cache_with(config);
+        test_util::add_member(&cache, GUILD, user_id(3), Vec::new()).await;
+
+        // Reading prunes right away so the next prune is not due for a while
+        assert_eq!(cache.stats().await.unwrap().members, 1);
+        assert_eq!(cache.user_guilds(user_id(3)).await.unwrap(), [GUILD]);
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        let stats = cache.stats().await.unwrap();
+        assert_eq!(stats.members, 0);
+        assert_eq!(stats.users, 0);
+        assert_eq!(cache.members(GUILD).await.unwrap().into_iter().count(), 0);
+        assert!(cache.user_guilds(user_id(3)).await.unwrap().is_empty());
+
+        let key = format!("{}:{}", GUILD_MEMBERS, GUILD);
+        assert_eq!(cache.backend.set_len(&key).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_presences_are_pruned_from_index() {
+        let config = CacheConfig {
+            cache_presences: true,
+            presence_ttl: Some(1),
+            ..Default::default()
+        };
+
+        let cache = test_util::cache_with(config);
+
+        let presence = PresenceUpdate {
+            activities: Vec::new(),
+            client_status: ClientStatus {
+                desktop: Some(Status::Online),
+                mobile: None,
+                web: None,
+            },
+            game: None,
+            guild_id: GUILD,
+            status: Status::Online,
+            user: UserOrId::UserId { id: user_id(3) },
+        };
+
+        let event = Event::PresenceUpdate(Box::new(presence));
+        cache.update(&event).await.unwrap();
+
+        let key = RedisKey::Presence {
+            guild: GUILD,
+            user: user_id(3),
+        }
+        .to_string();
+
+        let index = format!("{}:{}", GUILD_KEYS, GUILD);
+        let contains = || cache.backend.set_contains(&index, &key);
+        assert!(contains().await.unwrap());
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // Any read of a member index triggers the prune
+        cache.members(GUILD).await.unwrap();
+        assert!(!contains().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn users_are_removed_with_their_last_member() {
+        let cache = test_util::cache();
+        let other = guild_id(11);
+
+        test_util::add_member(&cache, GUILD, user_id(3), Vec::new()).await;
+        test_util::add_member(&cache, other, user_id(3), Vec::new()).await;
+
+        remove_member(&cache, 3).await;
+        assert!(cache.user(user_id(3)).await.unwrap().is_some());
+        assert_eq!(cache.stats().await.unwrap().users, 1);
+
+        let member = test_util::member(other, user_id(3), Vec::new());
+
+        let event = Event::MemberRemove(MemberRemove {
+            guild_id: other,
+            user: member.user,
+        });
+
+        cache.update(&event).await.unwrap();
+        assert!(cache.user(user_id(3)).await.unwrap().is_none());
+        assert_eq!(cache.stats().await.unwrap().users, 0);
+    }
+
+    #[tokio::test]
+    async fn guild_delete_removes_all_guild_entries() {
+        let cache = test_util::cache();
+        let role = role_id(20);
+
+        let mut guild = test_util::guild(GUILD, user_id(2));
+
+        guild.channels.push(GuildChannel::Text(TextChannel {
+            guild_id: Some(GUILD),
+            id: ChannelId::new(30).unwrap(),
+            kind: ChannelType::GuildText,
+            last_message_id: None,
+            last_pin_timestamp: None,
+            name: "general".to_owned(),
+            nsfw: false,
+            parent_id: None,
+            permission_overwrites: Vec::new(),
+            position: 0,
+            rate_limit_per_user: None,
+            topic: None,
+        }));
+
+        guild.emojis.push(Emoji {
+            animated: false,
+            available: true,
+            id: EmojiId::new(40).unwrap(),
+            managed: false,
+            name: "emoji".to_owned(),
+            require_colons: true,
+            roles: Vec::new(),
+            user: None,
+        });
+
+        guild.roles = vec![
+            test_util::role(role_id(10), Permissions::VIEW_CHANNEL),
+            test_util::role(role, Permissions::KICK_MEMBERS),
+        ];
+
+        guild.members = vec![
+            test_util::member(GUILD, user_id(3), vec![role]),
+            test_util::member(GUILD, user_id(4), Vec::new()),
+        ];
+
+        let event = Event::GuildCreate(Box::new(GuildCreate(guild)));
+        cache.update(&event).await.unwrap();
+
+        let stats = cache.stats().await.unwrap();
+        assert_eq!(stats.channels, 1);
+        assert_eq!(stats.members, 2);
+        assert_eq!(stats.roles, 2);
+        assert_eq!(stats.users, 2);
+
+        let event = Event::GuildDelete(Box::new(GuildDelete {
+            id: GUILD,
+            unavailable: false,
+        }));
+
+        cache.update(&event).await.unwrap();
+
+        let mut keys = vec![
+            format!("{}:{}", ROLE_MEMBERS, role),
+            format!("{}:{}", USER_GUILDS, user_id(3)),
+            format!("{}:{}", USER_GUILDS, user_id(4)),
+            format!("{}:{}", MEMBER_COUNT, GUILD),
+            RedisKey::from(GUILD).to_string(),
+        ];
+
+        for index in [
+            GUILD_KEYS,
+            GUILD_CHANNELS,
+            GUILD_ROLES,
+            GUILD_MEMBERS,
+            GUILD_EMOJIS,
+            GUILD_STICKERS,
+            GUILD_STAGE_INSTANCES,
+        ] {
+            keys.push(format!("{}:{}", index, GUILD));
+        }
+
+        for key in keys {
+            assert!(
+                !cache.backend.exists(&key).await.unwrap(),
+                "{} remained",
+                key
+            );
+        }
+
+        let stats = cache.stats().await.unwrap();
+        assert_eq!(stats.channels, 0);
+        assert_eq!(stats.guilds, 0);
+        assert_eq!(stats.members, 0);
+        assert_eq!(stats.roles, 0);
+        assert_eq!(stats.users, 0);
+    }
+
+    fn scheduled_event(id: u64, start: i64) -> ScheduledEvent {
+        ScheduledEvent {
+            channel_id: None,
+            creator_id: None,
+            description: None,
+            entity_metadata: None,
+            entity_type: ScheduledEventEntityType::External,
+            guild_id: GUILD,
+            id: ScheduledEventId::new(id).unwrap(),
+            image: None,
+            name: format!("event {}", id),
+            scheduled_end_time: None,
+            scheduled_start_time: Timestamp::from_secs(start).unwrap(),
+            status: ScheduledEventStatus::Scheduled,
+            user_count: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn scheduled_events_per_guild() {
+        let cache = test_util::cache();
+
+        let events = [scheduled_event(50, 2000), scheduled_event(51, 1000)];
+        cache.cache_scheduled_events(GUILD, &events).await.unwrap();
+
+        let mut update = scheduled_event(50, 2000);
+        update.status = ScheduledEventStatus::Active;
+        cache.cache_scheduled_event(&update).await.unwrap();
+
+        let cached = cache.guild_scheduled_events(GUILD).await.unwrap();
+        let ids: Vec<_> = cached.iter().map(|event| event.id.get()).collect();
+        assert_eq!(ids, [51, 50]);
+        assert!(cached[0].is_upcoming());
+        assert!(!cached[1].is_upcoming());
+
+        let id = ScheduledEventId::new(51).unwrap();
+        cache.delete_scheduled_event(GUILD, id).await.unwrap();
+        assert!(cache.scheduled_event(id).await.unwrap().is_none());
+        assert_eq!(cache.guild_scheduled_events(GUILD).await.unwrap().len(), 1);
+
+        let guild = test_util::guild(GUILD, user_id(2));
+        let event = Event::GuildCreate(Box::new(GuildCreate(guild)));
+        cache.update(&event).await.unwrap();
+        assert_eq!(cache.guild_scheduled_events(GUILD).await.unwrap().len(), 1);
+
+        let event = Event::GuildDelete(Box::new(GuildDelete {
+            id: GUILD,
+            unavailable: false,
+        }));
+
+        cache.update(&event).await.unwrap();
+        assert!(cache
+            .guild_scheduled_events(GUILD)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let key = format!("{}:{}", GUILD_SCHEDULED_EVENTS, GUILD);
+        assert!(!cache.backend.exists(&key).await.unwrap());
+    }
 }
@@ -0,0 +1,152 @@
+//! Fixtures for building a [`Cache`] on top of [`MemoryBackend`] and feeding it events
+
+use twilight_model::{
+    datetime::Timestamp,
+    gateway::{event::Event, payload::incoming::MemberAdd},
+    guild::{
+        DefaultMessageNotificationLevel, ExplicitContentFilter, Guild, Member, MfaLevel, NSFWLevel,
+        Permissions, PremiumTier, Role, SystemChannelFlags, VerificationLevel,
+    },
+    id::{GuildId, RoleId, UserId},
+    user::User,
+};
+
+use crate::{backend::MemoryBackend, model::CacheConfig, Cache};
+
+pub(crate) const BOT: UserId = user_id(1);
+
+pub(crate) const fn guild_id(id: u64) -> GuildId {
+    match GuildId::new(id) {
+        Some(id) => id,
+        None => panic!("ids must be non-zero"),
+    }
+}
+
+pub(crate) const fn role_id(id: u64) -> RoleId {
+    match RoleId::new(id) {
+        Some(id) => id,
+        None => panic!("ids must be non-zero"),
+    }
+}
+
+pub(crate) const fn user_id(id: u64) -> UserId {
+    match UserId::new(id) {
+        Some(id) => id,
+        None => panic!("ids must be non-zero"),
+    }
+}
+
+pub(crate) fn cache() -> Cache {
+    cache_with(CacheConfig::default())
+}
+
+pub(crate) fn cache_with(config: CacheConfig) -> Cache {
+    Cache::with_backend(MemoryBackend::new(), BOT, config)
+}
+
+pub(crate) fn guild(id: GuildId, owner: UserId) -> Guild {
+    Guild {
+        afk_channel_id: None,
+        afk_timeout: 300,
+        application_id: None,
+        approximate_member_count: None,
+        approximate_presence_count: None,
+        banner: None,
+        channels: Vec::new(),
+        default_message_notifications: DefaultMessageNotificationLevel::Mentions,
+        description: None,
+        discovery_splash: None,
+        emojis: Vec::new(),
+        explicit_content_filter: ExplicitContentFilter::None,
+        features: Vec::new(),
+        icon: None,
+        id,
+        joined_at: None,
+        large: false,
+        max_members: None,
+        max_presences: None,
+        max_video_channel_users: None,
+        member_count: Some(0),
+        members: Vec::new(),
+        mfa_level: MfaLevel::None,
+        name: "guild".to_owned(),
+        nsfw_level: NSFWLevel::Default,
+        owner_id: owner,
+        owner: None,
+        permissions: None,
+        preferred_locale: "en-US".to_owned(),
+        premium_progress_bar_enabled: false,
+        premium_subscription_count: None,
+        premium_tier: PremiumTier::None,
+        presences: Vec::new(),
+        roles: Vec::new(),
+        rules_channel_id: None,
+        splash: None,
+        stage_instances: Vec::new(),
+        stickers: Vec::new(),
+        system_channel_flags: SystemChannelFlags::empty(),
+        system_channel_id: None,
+        threads: Vec::new(),
+        unavailable: false,
+        vanity_url_code: None,
+        verification_level: VerificationLevel::None,
+        voice_states: Vec::new(),
+        widget_channel_id: None,
+        widget_enabled: None,
+    }
+}
+
+pub(crate) fn role(id: RoleId, permissions: Permissions) -> Role {
+    Role {
+        color: 0,
+        hoist: false,
+        icon: None,
+        id,
+        managed: false,
+        mentionable: false,
+        name: format!("role {}", id),
+        permissions,
+        position: 0,
+        tags: None,
+        unicode_emoji: None,
+    }
+}
+
+/// Cache the member through a MemberAdd event
+pub(crate) async fn add_member(cache: &Cache, guild: GuildId, user: UserId, roles: Vec<RoleId>) {
+    let event = Event::MemberAdd(Box::new(MemberAdd(member(guild, user, roles))));
+
+    cache.update(&event).await.unwrap();
+}
+
+pub(crate) fn member(guild: GuildId, user: UserId, roles: Vec<RoleId>) -> Member {
+    Member {
+        avatar: None,
+        communication_disabled_until: None,
+        deaf: false,
+        guild_id: guild,
+        joined_at: Timestamp::from_secs(1_632_072_645).expect("valid timestamp"),
+        mute: false,
+        nick: None,
+        pending: false,
+        premium_since: None,
+        roles,
+        user: User {
+            accent_color: None,
+            avatar: None,
+            banner: None,
+            bot: false,
+            discriminator: 1,
+            email: None,
+            flags: None,
+            id: user,
+            locale: None,
+            mfa_enabled: None,
+            name: format!("user {}", user),
+            premium_type: None,
+            public_flags: None,
+            system: None,
+            verified: None,
+        },
+    }
+}
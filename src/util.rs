@@ -1,21 +1,26 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, iter};
 
-use deadpool_redis::redis::AsyncCommands;
 use twilight_model::{
-    channel::permission_overwrite::PermissionOverwriteType, guild::Permissions, id::UserId,
+    channel::permission_overwrite::PermissionOverwriteType,
+    guild::Permissions,
+    id::{ChannelId, RoleId, UserId},
 };
 
 use crate::{
-    constants::{CHANNEL_KEYS, GUILD_KEYS, MEMBER_KEYS, OWNER_USER_ID, ROLE_KEYS},
+    constants::{CHANNEL_KEYS, GUILD_KEYS, MEMBER_KEYS, ROLE_KEYS, USER_KEYS},
     model::{
-        CacheStats, CachedChannel, CachedMember, CachedTextChannel, ChannelOrId, GuildOrId,
-        MemberLookup, RedisKey,
+        CacheStats, CachedChannel, CachedMember, ChannelOrId, GuildOrId, MemberLookup, RedisKey,
     },
     CacheError, CacheResult,
 };
 
 use super::Cache;
 
+/// Timed out members can only view channels and read their history
+const TIMED_OUT_PERMISSIONS: Permissions = Permissions::from_bits_truncate(
+    Permissions::VIEW_CHANNEL.bits() | Permissions::READ_MESSAGE_HISTORY.bits(),
+);
+
 impl Cache {
     #[inline]
     pub async fn is_guild_owner(&self, guild: &GuildOrId, user: UserId) -> CacheResult<bool> {
@@ -31,29 +36,55 @@ impl Cache {
 
     #[inline]
     pub async fn contains(&self, key: impl Into<RedisKey>) -> CacheResult<bool> {
-        Ok(self.redis.get().await?.exists(key.into()).await?)
+        let key = key.into().to_string();
+
+        self.backend.exists(&self.prefixed(&key)).await
     }
 
     pub async fn stats(&self) -> CacheResult<CacheStats> {
-        let mut conn = self.redis.get().await?;
+        self.prune_if_due().await?;
+
+        // Expired keys are read first so that keys pruned in between are counted by neither
+        let (expired_members, expired_users): (Vec<_>, Vec<_>) = self
+            .expired_keys()
+            .await?
+            .into_iter()
+            .filter(|key| matches!(key, RedisKey::Member { .. } | RedisKey::User { .. }))
+            .partition(|key| matches!(key, RedisKey::Member { .. }));
 
         let stats = CacheStats {
-            channels: conn.scard(CHANNEL_KEYS).await?,
-            guilds: conn.scard(GUILD_KEYS).await?,
-            members: conn.scard(MEMBER_KEYS).await?,
-            roles: conn.scard(ROLE_KEYS).await?,
+            channels: self.backend.set_len(&self.prefixed(CHANNEL_KEYS)).await?,
+            guilds: self.backend.set_len(&self.prefixed(GUILD_KEYS)).await?,
+            members: self.live_len(MEMBER_KEYS, expired_members).await?,
+            roles: self.backend.set_len(&self.prefixed(ROLE_KEYS)).await?,
+            users: self.live_len(USER_KEYS, expired_users).await?,
         };
 
         Ok(stats)
     }
 
+    /// Amount of entries in the index set without the expired keys that are yet to be pruned
+    async fn live_len(&self, index: &str, expired: Vec<RedisKey>) -> CacheResult<usize> {
+        let index = self.prefixed(index);
+        let len = self.backend.set_len(&index).await?;
+        let mut pending = 0;
+
+        for key in expired {
+            if self.backend.set_contains(&index, &key.to_string()).await? {
+                pending += 1;
+            }
+        }
+
+        Ok(len.saturating_sub(pending))
+    }
+
     pub async fn get_guild_permissions(
         &self,
         user: UserId,
         guild: &GuildOrId,
     ) -> CacheResult<(Permissions, MemberLookup)> {
-        if user.get() == OWNER_USER_ID {
-            return Ok((Permissions::all(), MemberLookup::NotChecked));
+        if self.config.superusers.contains(&user) {
+            return Ok((Permissions::all(), MemberLookup::Superuser));
         }
 
         match self.is_guild_owner(guild, user).await {
@@ -70,16 +101,25 @@ impl Cache {
             None => return Ok((Permissions::empty(), MemberLookup::NotFound)),
         };
 
-        let mut permissions = Permissions::empty();
+        // Members don't list the @everyone role but it provides the base permissions
+        let everyone = RoleId(guild.id().0);
 
-        for &role_id in &member.roles {
-            if let Some(role) = self.role(role_id).await? {
-                if role.permissions.contains(Permissions::ADMINISTRATOR) {
-                    return Ok((Permissions::all(), MemberLookup::Found(member)));
-                }
+        let role_ids: Vec<_> = iter::once(everyone)
+            .chain(member.roles.iter().copied())
+            .collect();
 
-                permissions |= role.permissions;
+        let mut permissions = Permissions::empty();
+
+        for role in self.roles(&role_ids).await? {
+            if role.permissions.contains(Permissions::ADMINISTRATOR) {
+                return Ok((Permissions::all(), MemberLookup::Found(member)));
             }
+
+            permissions |= role.permissions;
+        }
+
+        if member.is_timed_out() {
+            permissions &= TIMED_OUT_PERMISSIONS;
         }
 
         Ok((permissions, MemberLookup::Found(member)))
@@ -111,59 +151,92 @@ impl Cache {
             return Ok(Permissions::all());
         }
 
-        let channel = self.extract_channel(channel).await?;
+        let channel = match channel {
+            ChannelOrId::Channel(channel) => Cow::Borrowed(channel),
+            ChannelOrId::Id(id) => match self.channel(*id).await? {
+                Some(channel) => Cow::Owned(channel),
+                None => return Ok(permissions),
+            },
+        };
+
+        // Threads use the permission overwrites of their parent channel
+        let parent = if channel.is_thread() {
+            match channel.parent_id() {
+                Some(parent) => self.channel(parent).await?.map(Cow::Owned),
+                None => None,
+            }
+        } else {
+            Some(Cow::Borrowed(channel.as_ref()))
+        };
 
-        if let Some(channel) = channel {
+        if let Some(parent) = parent {
             let member = match member {
                 MemberLookup::Found(member) => Some(member),
-                MemberLookup::NotChecked => self.member(guild.id(), user).await?,
+                MemberLookup::NotChecked | MemberLookup::Superuser => {
+                    self.member(guild.id(), user).await?
+                }
                 MemberLookup::NotFound => None,
             };
 
             if let Some(member) = member {
-                Self::text_channel_permissions(&mut permissions, user, guild, channel, member)
+                Self::channel_permissions(&mut permissions, user, guild, parent, member)
+            }
+        }
+
+        if channel.is_thread() {
+            // Sending messages in threads has its own permission
+            let send_in_threads = permissions.contains(Permissions::SEND_MESSAGES_IN_THREADS);
+            permissions.set(Permissions::SEND_MESSAGES, send_in_threads);
+
+            // Private threads are only accessible to their members and thread managers.
+            // If the thread's members are not all known, the parent's permissions are used.
+            if let CachedChannel::PrivateThread(thread) = channel.as_ref() {
+                if !permissions.contains(Permissions::MANAGE_THREADS)
+                    && self.thread_membership(thread.id, user).await? == Some(false)
+                {
+                    return Ok(Permissions::empty());
+                }
             }
         }
 
         Ok(permissions)
     }
 
-    #[allow(clippy::needless_lifetimes)]
-    async fn extract_channel<'c>(
-        &self,
-        channel: &'c ChannelOrId,
-    ) -> CacheResult<Option<Cow<'c, CachedTextChannel>>> {
-        let id = match channel {
-            ChannelOrId::Channel(CachedChannel::Text(channel)) => {
-                return Ok(Some(Cow::Borrowed(channel)))
-            }
-            ChannelOrId::Channel(
-                CachedChannel::PrivateThread(channel) | CachedChannel::PublicThread(channel),
-            ) => channel.id,
-            ChannelOrId::Id(id) => *id,
-        };
-
-        match self.channel(id).await? {
-            Some(CachedChannel::Text(c)) => return Ok(Some(Cow::Owned(c))),
-            Some(CachedChannel::PrivateThread(thread))
-            | Some(CachedChannel::PublicThread(thread)) => {
-                if let Some(parent) = thread.parent_id {
-                    if let Some(CachedChannel::Text(channel)) = self.channel(parent).await? {
-                        return Ok(Some(Cow::Owned(channel)));
-                    }
+    /// Retrieve the channel followed by its parents i.e. a thread's parent channel
+    /// and a channel's category, as far as they are cached
+    pub async fn channel_parents(&self, channel: ChannelId) -> CacheResult<Vec<CachedChannel>> {
+        let mut chain = Vec::with_capacity(3);
+        let mut next = Some(channel);
+
+        // Thread -> Channel -> Category
+        while let Some(id) = next.filter(|_| chain.len() < 3) {
+            match self.channel(id).await? {
+                Some(channel) => {
+                    next = channel.parent_id();
+                    chain.push(channel);
                 }
+                None => break,
             }
-            _ => {}
         }
 
-        Ok(None)
+        Ok(chain)
+    }
+
+    /// Check whether the channel is age-restricted, threads are checked via their parent channel.
+    ///
+    /// Returns `None` if the channel or a thread's parent channel is not cached
+    /// in which case callers gating content should refuse.
+    pub async fn is_nsfw_channel(&self, channel: ChannelId) -> CacheResult<Option<bool>> {
+        let chain = self.channel_parents(channel).await?;
+
+        Ok(chain.iter().find_map(CachedChannel::is_nsfw))
     }
 
-    fn text_channel_permissions(
+    fn channel_permissions(
         permissions: &mut Permissions,
         user: UserId,
         guild: &GuildOrId,
-        channel: Cow<'_, CachedTextChannel>,
+        channel: Cow<'_, CachedChannel>,
         member: CachedMember,
     ) {
         let mut everyone_allowed = Permissions::empty();
@@ -173,7 +246,7 @@ impl Cache {
         let mut role_allowed = Permissions::empty();
         let mut role_denied = Permissions::empty();
 
-        for overwrite in &channel.permission_overwrites {
+        for overwrite in channel.permission_overwrites() {
             match overwrite.kind {
                 PermissionOverwriteType::Member(member) => {
                     if member == user {
@@ -201,5 +274,106 @@ impl Cache {
 
         *permissions &= !user_denied;
         *permissions |= user_allowed;
+
+        if member.is_timed_out() {
+            *permissions &= TIMED_OUT_PERMISSIONS;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        gateway::{
+            event::Event,
+            payload::incoming::{GuildCreate, RoleCreate},
+        },
+        guild::{Permissions, Role},
+        id::{GuildId, RoleId},
+    };
+
+    use crate::{
+        model::{GuildOrId, MemberLookup},
+        test_util::{self, guild_id, role_id, user_id},
+        Cache,
+    };
+
+    const GUILD: GuildId = guild_id(10);
+    const EVERYONE: RoleId = role_id(10);
+
+    async fn setup(roles: Vec<Role>) -> Cache {
+        let cache = test_util::cache();
+        let guild = test_util::guild(GUILD, user_id(2));
+        cache
+            .update(&Event::GuildCreate(Box::new(GuildCreate(guild))))
+            .await
+            .unwrap();
+
+        for role in roles {
+            let event = RoleCreate {
+                guild_id: GUILD,
+                role,
+            };
+
+            cache.update(&Event::RoleCreate(event)).await.unwrap();
+        }
+
+        cache
+    }
+
+    #[tokio::test]
+    async fn everyone_permissions_without_roles() {
+        let everyone = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+        let moderator = role_id(20);
+
+        let cache = setup(vec![
+            test_util::role(EVERYONE, everyone),
+            test_util::role(moderator, Permissions::KICK_MEMBERS),
+        ])
+        .await;
+
+        test_util::add_member(&cache, GUILD, user_id(3), Vec::new()).await;
+
+        let (permissions, lookup) = cache
+            .get_guild_permissions(user_id(3), &GuildOrId::Id(GUILD))
+            .await
+            .unwrap();
+
+        assert_eq!(permissions, everyone);
+        assert!(matches!(lookup, MemberLookup::Found(member) if member.roles.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn administrator_grants_all_permissions() {
+        let admin = role_id(20);
+
+        let cache = setup(vec![
+            test_util::role(EVERYONE, Permissions::VIEW_CHANNEL),
+            test_util::role(admin, Permissions::ADMINISTRATOR),
+        ])
+        .await;
+
+        test_util::add_member(&cache, GUILD, user_id(3), vec![admin]).await;
+
+        let (permissions, lookup) = cache
+            .get_guild_permissions(user_id(3), &GuildOrId::Id(GUILD))
+            .await
+            .unwrap();
+
+        assert_eq!(permissions, Permissions::all());
+        assert!(matches!(lookup, MemberLookup::Found(_)));
+    }
+
+    #[tokio::test]
+    async fn owner_grants_all_permissions() {
+        let cache = setup(vec![test_util::role(EVERYONE, Permissions::empty())]).await;
+
+        let (permissions, lookup) = cache
+            .get_guild_permissions(user_id(2), &GuildOrId::Id(GUILD))
+            .await
+            .unwrap();
+
+        assert_eq!(permissions, Permissions::all());
+        assert!(matches!(lookup, MemberLookup::NotChecked));
     }
 }